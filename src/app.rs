@@ -0,0 +1,894 @@
+use piston_window::*;
+use rust_snake_game::{
+    paths, Bot, BotKind, BoundaryMode, Difficulty, Direction, Game, GameSettings, HighScore,
+    HighScoreTable, RoundResult, ScoreCategory, StatsFormat, Versus, MAX_GRID_SIZE, MAX_NAME_LEN,
+    MIN_GRID_SIZE,
+};
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::board::{self, Layout, MAX_CELL_SIZE, MIN_CELL_SIZE};
+use crate::config::{Action, Config};
+use crate::hud::{self, Menu};
+use crate::{new_events, open_window};
+
+/// Name used when the player confirms an empty name.
+const DEFAULT_PLAYER_NAME: &str = "Player";
+
+const TITLE_ITEMS: [&str; 5] = ["Play", "Versus", "Settings", "High scores", "Quit"];
+const PAUSED_ITEMS: [&str; 3] = ["Resume", "Restart", "Main menu"];
+const GAME_OVER_ITEMS: [&str; 3] = ["Play again", "Main menu", "High scores"];
+
+/// Rows of the new game screen.
+const NEW_GAME_MODE: usize = 0;
+const NEW_GAME_DIFFICULTY: usize = 1;
+const NEW_GAME_START: usize = 2;
+const NEW_GAME_BACK: usize = 3;
+
+/// Rows of the settings screen.
+const SETTINGS_WIDTH: usize = 0;
+const SETTINGS_HEIGHT: usize = 1;
+const SETTINGS_CELL_SIZE: usize = 2;
+const SETTINGS_STATS_FORMAT: usize = 3;
+const SETTINGS_BACK: usize = 4;
+
+/// What the window is showing. Menus remember which row is selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Screen {
+    Title {
+        selected: usize,
+    },
+    /// Boundary mode and difficulty for the next game, or the next versus
+    /// round if `versus`
+    NewGame {
+        selected: usize,
+        versus: bool,
+    },
+    Settings {
+        selected: usize,
+    },
+    HighScores {
+        back: Box<Screen>,
+    },
+    Playing,
+    Paused {
+        selected: usize,
+    },
+    /// `name` is Some while the player types a name for a new high score
+    GameOver {
+        name: Option<String>,
+        selected: usize,
+    },
+}
+
+/// Menu movement, from the arrow keys or the configured turn keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Nav {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+/// A play session in a window, from the title screen through any number of
+/// games.
+pub struct App {
+    config: Config,
+    seed: Option<u64>,
+    settings: GameSettings,
+    game: Game,
+    /// Some while a versus round is on, which then takes the place of `game`
+    versus: Option<Versus>,
+    screen: Screen,
+    scores_path: PathBuf,
+    high_scores: HighScoreTable,
+    stats_dir: PathBuf,
+    replay_dir: PathBuf,
+    /// Some while the autopilot is playing; games it touched don't get high scores
+    autopilot: Option<(usize, Box<dyn Bot>)>,
+    bot_assisted: bool,
+    /// Why the last file couldn't be loaded or saved, shown at the bottom
+    /// of the board rather than printed, which would garble the terminal
+    error: Option<String>,
+    quit: bool,
+}
+
+impl App {
+    /// Opens on the title screen, or straight into a game if `skip_menus`.
+    pub fn new(config: Config, seed: Option<u64>, skip_menus: bool) -> Self {
+        let settings = config.game_settings();
+        let scores_path = HighScoreTable::default_path();
+        let (high_scores, error) = match HighScoreTable::load(&scores_path) {
+            Ok(high_scores) => (high_scores, None),
+            Err(e) => (
+                HighScoreTable::default(),
+                Some(format!("Error loading high scores: {}", e)),
+            ),
+        };
+
+        let mut app = Self {
+            stats_dir: config.stats_dir(),
+            replay_dir: paths::default_replay_dir(),
+            config,
+            seed,
+            settings,
+            game: new_game(settings, seed),
+            versus: None,
+            screen: Screen::Title { selected: 0 },
+            scores_path,
+            high_scores,
+            autopilot: None,
+            bot_assisted: false,
+            error,
+            quit: false,
+        };
+        if skip_menus {
+            app.start_game();
+        }
+        app
+    }
+
+    pub fn run(mut self) -> Result<(), Box<dyn Error>> {
+        let mut window_size = self.layout().window_size();
+        let mut window = open_window("Snake Game", &self.layout(), false)?;
+        let mut glyphs = hud::load_font(&mut window);
+        let mut events = new_events();
+
+        while let Some(e) = events.next(&mut window) {
+            if let Some(text) = e.text_args() {
+                self.on_text(&text);
+            }
+            if let Some(Button::Keyboard(key)) = e.press_args() {
+                self.on_key(key);
+            }
+            if e.focus_args() == Some(false) {
+                self.lose_focus();
+            }
+            if let Some(update_args) = e.update_args() {
+                self.update(update_args.dt);
+            }
+
+            if self.is_quitting() {
+                window.set_should_close(true);
+            }
+            // Follow board and cell size changes from the settings screen
+            if self.layout().window_size() != window_size {
+                window_size = self.layout().window_size();
+                window.set_size(window_size);
+            }
+
+            window.draw_2d(&e, |c, g, device| {
+                self.draw(&c, g, glyphs.as_mut());
+                if let Some(glyphs) = glyphs.as_mut() {
+                    glyphs.factory.encoder.flush(device);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The single-player game, which is also the board behind the menus.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The versus round, while one is on.
+    pub fn versus(&self) -> Option<&Versus> {
+        self.versus.as_ref()
+    }
+
+    /// The high score for the current board and difficulty.
+    pub fn best(&self) -> u32 {
+        self.high_scores.best(&self.category())
+    }
+
+    /// Whether the player chose to quit.
+    pub fn is_quitting(&self) -> bool {
+        self.quit
+    }
+
+    /// Stops the clock when the player switches to another window.
+    pub fn lose_focus(&mut self) {
+        if self.screen == Screen::Playing {
+            self.pause();
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::new(&self.settings, &self.config.display)
+    }
+
+    fn category(&self) -> ScoreCategory {
+        ScoreCategory::new(self.settings)
+    }
+
+    /// Presets, plus the curve from the config file if it has one.
+    fn difficulties(&self) -> Vec<Difficulty> {
+        let mut difficulties = Difficulty::PRESETS.to_vec();
+        if self.config.speed.is_some() {
+            difficulties.push(Difficulty::Custom);
+        }
+        difficulties
+    }
+
+    fn nav(&self, key: Key) -> Option<Nav> {
+        match (key, self.config.keys.action(key)) {
+            (Key::Up, _) | (_, Some(Action::Turn(Direction::Up))) => Some(Nav::Up),
+            (Key::Down, _) | (_, Some(Action::Turn(Direction::Down))) => Some(Nav::Down),
+            (Key::Left, _) | (_, Some(Action::Turn(Direction::Left))) => Some(Nav::Left),
+            (Key::Right, _) | (_, Some(Action::Turn(Direction::Right))) => Some(Nav::Right),
+            (Key::Return | Key::Space, _) => Some(Nav::Select),
+            (Key::Escape | Key::Backspace, _) => Some(Nav::Back),
+            _ => None,
+        }
+    }
+
+    /// A fresh game with the current settings, keeping the autopilot on if
+    /// it was.
+    fn start_game(&mut self) {
+        self.game = new_game(self.settings, self.seed);
+        self.versus = None;
+        // Bots are built for one board size, so rebuild in case it changed
+        self.autopilot = self
+            .autopilot
+            .take()
+            .and_then(|(i, _)| BotKind::ALL[i].build(&self.settings).map(|bot| (i, bot)));
+        self.bot_assisted = self.autopilot.is_some();
+        self.error = None;
+        self.screen = Screen::Playing;
+    }
+
+    /// A fresh two-player round with the current settings.
+    fn start_versus(&mut self) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        self.versus = Some(Versus::with_settings(self.settings, seed));
+        self.error = None;
+        self.screen = Screen::Playing;
+    }
+
+    /// Starts another game of the same kind as the last one.
+    fn play_again(&mut self) {
+        match self.versus {
+            Some(_) => self.start_versus(),
+            None => self.start_game(),
+        }
+    }
+
+    /// Back to the title screen with an untouched board behind it.
+    fn back_to_title(&mut self) {
+        self.game = new_game(self.settings, self.seed);
+        self.versus = None;
+        self.screen = Screen::Title { selected: 0 };
+    }
+
+    fn pause(&mut self) {
+        self.set_paused(true);
+        self.screen = Screen::Paused { selected: 0 };
+    }
+
+    fn resume(&mut self) {
+        self.set_paused(false);
+        self.screen = Screen::Playing;
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        match self.versus.as_mut() {
+            Some(versus) => versus.set_paused(paused),
+            None => self.game.set_paused(paused),
+        }
+    }
+
+    /// Saves the finished game and moves on to the game-over screen.
+    fn finish_game(&mut self) {
+        let qualifies = self
+            .high_scores
+            .qualifies(&self.category(), self.game.score());
+        let name = (qualifies && !self.bot_assisted).then(String::new);
+
+        // Save stats and replay to file when game is over or won
+        if let Err(e) = self
+            .game
+            .save_stats(&self.stats_dir, self.config.stats.format)
+        {
+            self.error = Some(format!("Error saving stats: {}", e));
+        }
+        if let Err(e) = self.game.save_replay(&self.replay_dir) {
+            self.error = Some(format!("Error saving replay: {}", e));
+        }
+
+        self.screen = Screen::GameOver { name, selected: 0 };
+    }
+
+    fn record_high_score(&mut self, name: &str) {
+        let name = match name.trim() {
+            "" => DEFAULT_PLAYER_NAME,
+            name => name,
+        };
+        let category = self.category();
+        self.high_scores
+            .insert(&category, HighScore::from_game(name, &self.game));
+        if let Err(e) = self.high_scores.save(&self.scores_path) {
+            self.error = Some(format!("Error saving high scores: {}", e));
+        }
+    }
+
+    fn show_high_scores(&mut self) {
+        let back = Box::new(self.screen.clone());
+        self.screen = Screen::HighScores { back };
+    }
+
+    pub fn on_text(&mut self, text: &str) {
+        if let Screen::GameOver {
+            name: Some(name), ..
+        } = &mut self.screen
+        {
+            for ch in text.chars().filter(|ch| !ch.is_control()) {
+                if name.chars().count() < MAX_NAME_LEN {
+                    name.push(ch);
+                }
+            }
+        }
+    }
+
+    pub fn on_key(&mut self, key: Key) {
+        let action = self.config.keys.action(key);
+        let nav = self.nav(key);
+
+        match self.screen.clone() {
+            Screen::Title { selected } => match nav {
+                Some(Nav::Up) => {
+                    self.screen = Screen::Title {
+                        selected: up(selected),
+                    }
+                }
+                Some(Nav::Down) => {
+                    let selected = down(selected, TITLE_ITEMS.len());
+                    self.screen = Screen::Title { selected };
+                }
+                Some(Nav::Select) => match selected {
+                    0 | 1 => {
+                        self.screen = Screen::NewGame {
+                            selected: NEW_GAME_START,
+                            versus: selected == 1,
+                        }
+                    }
+                    2 => self.screen = Screen::Settings { selected: 0 },
+                    3 => self.show_high_scores(),
+                    _ => self.quit = true,
+                },
+                Some(Nav::Back) => self.quit = true,
+                _ if action == Some(Action::HighScores) => self.show_high_scores(),
+                _ => {}
+            },
+            Screen::NewGame { selected, versus } => match nav {
+                Some(Nav::Up) => {
+                    self.screen = Screen::NewGame {
+                        selected: up(selected),
+                        versus,
+                    }
+                }
+                Some(Nav::Down) => {
+                    self.screen = Screen::NewGame {
+                        selected: down(selected, NEW_GAME_BACK + 1),
+                        versus,
+                    }
+                }
+                Some(Nav::Left) => self.change_new_game(selected, -1),
+                Some(Nav::Right) => self.change_new_game(selected, 1),
+                Some(Nav::Select) => match selected {
+                    NEW_GAME_START if versus => self.start_versus(),
+                    NEW_GAME_START => self.start_game(),
+                    NEW_GAME_BACK => {
+                        self.screen = Screen::Title {
+                            selected: usize::from(versus),
+                        }
+                    }
+                    _ => self.change_new_game(selected, 1),
+                },
+                Some(Nav::Back) => {
+                    self.screen = Screen::Title {
+                        selected: usize::from(versus),
+                    }
+                }
+                None => {}
+            },
+            Screen::Settings { selected } => match nav {
+                Some(Nav::Up) => {
+                    self.screen = Screen::Settings {
+                        selected: up(selected),
+                    }
+                }
+                Some(Nav::Down) => {
+                    let selected = down(selected, SETTINGS_BACK + 1);
+                    self.screen = Screen::Settings { selected };
+                }
+                Some(Nav::Left) => self.change_setting(selected, -1),
+                Some(Nav::Right) => self.change_setting(selected, 1),
+                Some(Nav::Select) if selected != SETTINGS_BACK => self.change_setting(selected, 1),
+                Some(Nav::Select | Nav::Back) => self.screen = Screen::Title { selected: 2 },
+                None => {}
+            },
+            Screen::HighScores { back } => {
+                if matches!(nav, Some(Nav::Select | Nav::Back))
+                    || action == Some(Action::HighScores)
+                {
+                    self.screen = *back;
+                }
+            }
+            Screen::Playing if self.versus.is_some() => {
+                if let Some((player, direction)) = self.config.keys.versus_turn(key) {
+                    if let Some(versus) = self.versus.as_mut() {
+                        versus.change_direction(player, direction);
+                    }
+                } else if action == Some(Action::Pause) || nav == Some(Nav::Back) {
+                    self.pause();
+                }
+            }
+            Screen::Playing => match action {
+                Some(Action::Turn(direction)) if self.autopilot.is_none() => {
+                    self.game.change_direction(direction)
+                }
+                Some(Action::Autopilot) => {
+                    let current = self.autopilot.as_ref().map(|(i, _)| *i);
+                    self.autopilot = next_autopilot(current, &self.settings);
+                    self.bot_assisted |= self.autopilot.is_some();
+                }
+                Some(Action::Pause) => self.pause(),
+                _ if nav == Some(Nav::Back) => self.pause(),
+                _ => {}
+            },
+            Screen::Paused { selected } => match nav {
+                _ if action == Some(Action::Pause) => self.resume(),
+                Some(Nav::Up) => {
+                    self.screen = Screen::Paused {
+                        selected: up(selected),
+                    }
+                }
+                Some(Nav::Down) => {
+                    let selected = down(selected, PAUSED_ITEMS.len());
+                    self.screen = Screen::Paused { selected };
+                }
+                Some(Nav::Select) => match selected {
+                    0 => self.resume(),
+                    1 => self.play_again(),
+                    _ => self.back_to_title(),
+                },
+                Some(Nav::Back) => self.resume(),
+                _ => {}
+            },
+            Screen::GameOver {
+                name: Some(name), ..
+            } => match key {
+                Key::Return => {
+                    self.record_high_score(&name);
+                    self.screen = Screen::GameOver {
+                        name: None,
+                        selected: 0,
+                    };
+                    self.show_high_scores();
+                }
+                Key::Backspace => {
+                    if let Screen::GameOver {
+                        name: Some(name), ..
+                    } = &mut self.screen
+                    {
+                        name.pop();
+                    }
+                }
+                Key::Escape => {
+                    self.screen = Screen::GameOver {
+                        name: None,
+                        selected: 0,
+                    }
+                }
+                _ => {}
+            },
+            Screen::GameOver {
+                name: None,
+                selected,
+            } => match nav {
+                _ if action == Some(Action::Restart) => self.play_again(),
+                _ if action == Some(Action::HighScores) => self.show_high_scores(),
+                Some(Nav::Up) => {
+                    self.screen = Screen::GameOver {
+                        name: None,
+                        selected: up(selected),
+                    }
+                }
+                Some(Nav::Down) => {
+                    self.screen = Screen::GameOver {
+                        name: None,
+                        selected: down(selected, GAME_OVER_ITEMS.len()),
+                    }
+                }
+                Some(Nav::Select) => match selected {
+                    0 => self.play_again(),
+                    1 => self.back_to_title(),
+                    _ => self.show_high_scores(),
+                },
+                Some(Nav::Back) => self.back_to_title(),
+                _ => {}
+            },
+        }
+    }
+
+    /// Steps the mode or difficulty on the new game screen by `step`.
+    fn change_new_game(&mut self, selected: usize, step: isize) {
+        match selected {
+            NEW_GAME_MODE => {
+                self.settings.boundary = cycle(&BoundaryMode::ALL, self.settings.boundary, step)
+            }
+            NEW_GAME_DIFFICULTY => {
+                let difficulty = cycle(&self.difficulties(), self.settings.difficulty, step);
+                match (difficulty, self.config.speed) {
+                    (Difficulty::Custom, Some(speed)) => self.settings.set_custom_speed(speed),
+                    _ => self.settings.set_difficulty(difficulty),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Steps the setting on `selected` by `step`. Board changes redraw the
+    /// preview board and resize the window.
+    fn change_setting(&mut self, selected: usize, step: i64) {
+        let nudge = |value: u32, min: u32, max: u32| {
+            (value as i64 + step).clamp(min as i64, max as i64) as u32
+        };
+        match selected {
+            SETTINGS_WIDTH => {
+                self.settings.width = nudge(self.settings.width, MIN_GRID_SIZE, MAX_GRID_SIZE)
+            }
+            SETTINGS_HEIGHT => {
+                self.settings.height = nudge(self.settings.height, MIN_GRID_SIZE, MAX_GRID_SIZE)
+            }
+            SETTINGS_CELL_SIZE => {
+                let display = &mut self.config.display;
+                display.cell_size = nudge(display.cell_size, MIN_CELL_SIZE, MAX_CELL_SIZE)
+            }
+            SETTINGS_STATS_FORMAT => {
+                let stats = &mut self.config.stats;
+                stats.format = cycle(&StatsFormat::ALL, stats.format, step as isize)
+            }
+            _ => {}
+        }
+        self.game = new_game(self.settings, self.seed);
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        if self.screen != Screen::Playing {
+            return;
+        }
+        if let Some(versus) = self.versus.as_mut() {
+            if versus.update(dt) && versus.is_finished() {
+                // Versus rounds stay off the high score table
+                if let Err(e) = versus.save_stats(&self.stats_dir, self.config.stats.format) {
+                    self.error = Some(format!("Error saving stats: {}", e));
+                }
+                self.screen = Screen::GameOver {
+                    name: None,
+                    selected: 0,
+                };
+            }
+            return;
+        }
+        let ticked = match self.autopilot.as_mut() {
+            Some((_, bot)) => self.game.update_with(dt, |game| bot.drive(game)),
+            None => self.game.update(dt),
+        };
+        if ticked && self.game.is_finished() {
+            self.finish_game();
+        }
+    }
+
+    fn draw(&self, c: &Context, g: &mut G2d, glyphs: Option<&mut Glyphs>) {
+        let layout = self.layout();
+        clear(self.config.display.background.0, g);
+        match &self.versus {
+            Some(versus) => board::draw_versus(versus, &layout, &self.config.display, c, g),
+            None => board::draw(&self.game, &layout, &self.config.display, c, g),
+        }
+
+        let Some(glyphs) = glyphs else {
+            return;
+        };
+        let [width, height] = layout.window_size().map(f64::from);
+        match &self.versus {
+            Some(versus) => hud::draw_versus(versus, glyphs, width, c, g),
+            None => hud::draw(&self.game, Some(self.best()), glyphs, width, c, g),
+        }
+        if let Some(menu) = self.menu() {
+            hud::draw_menu(&menu, width, height, glyphs, c, g);
+        }
+        if let Some(status) = self.status() {
+            hud::draw_centered(&status, height - 12.0, width, glyphs, c, g);
+        }
+    }
+
+    /// The menu the current screen shows over the board, if any.
+    pub fn menu(&self) -> Option<Menu> {
+        let settings = &self.settings;
+        let menu_hint = "Up/Down to choose, Enter to select";
+        let change_hint = "Up/Down to choose, Left/Right to change";
+        let menu = match &self.screen {
+            Screen::Title { selected } => Menu {
+                title: "Snake".to_string(),
+                lines: vec![format!(
+                    "{} {}x{}, {}",
+                    settings.boundary, settings.width, settings.height, settings.difficulty
+                )],
+                items: TITLE_ITEMS.map(String::from).to_vec(),
+                selected: *selected,
+                hint: "Up/Down to choose, Enter to select, Esc to quit".to_string(),
+            },
+            Screen::NewGame { selected, versus } => {
+                let items = vec![
+                    format!("Mode: < {} >", settings.boundary),
+                    format!("Difficulty: < {} >", settings.difficulty),
+                    "Start".to_string(),
+                    "Back".to_string(),
+                ];
+                let (title, lines) = if *versus {
+                    let keys = &self.config.keys;
+                    let controls = vec![
+                        format!(
+                            "Player 1: {:?} {:?} {:?} {:?}",
+                            keys.up.0, keys.left.0, keys.down.0, keys.right.0
+                        ),
+                        format!(
+                            "Player 2: {:?} {:?} {:?} {:?}",
+                            keys.player2_up.0,
+                            keys.player2_left.0,
+                            keys.player2_down.0,
+                            keys.player2_right.0
+                        ),
+                    ];
+                    ("New versus round", controls)
+                } else {
+                    ("New game", Vec::new())
+                };
+                Menu {
+                    title: title.to_string(),
+                    lines,
+                    items,
+                    selected: *selected,
+                    hint: change_hint.to_string(),
+                }
+            }
+            Screen::Settings { selected } => Menu {
+                title: "Settings".to_string(),
+                lines: vec!["Changes last until the game is closed".to_string()],
+                items: vec![
+                    format!("Board width: < {} >", settings.width),
+                    format!("Board height: < {} >", settings.height),
+                    format!("Cell size: < {} >", self.config.display.cell_size),
+                    format!("Stats format: < {} >", self.config.stats.format),
+                    "Back".to_string(),
+                ],
+                selected: *selected,
+                hint: change_hint.to_string(),
+            },
+            Screen::HighScores { .. } => {
+                let category = self.category();
+                let entries = self.high_scores.entries(&category);
+                hud::high_scores_menu(entries, format!("High scores - {}", category.key()))
+            }
+            Screen::Playing => return None,
+            Screen::Paused { selected } => Menu {
+                title: "Paused".to_string(),
+                lines: Vec::new(),
+                items: PAUSED_ITEMS.map(String::from).to_vec(),
+                selected: *selected,
+                hint: format!(
+                    "{} - {:?} or Esc to resume",
+                    menu_hint, self.config.keys.pause.0
+                ),
+            },
+            Screen::GameOver { selected, .. } if self.versus.is_some() => {
+                let versus = self.versus.as_ref().expect("checked by the guard");
+                let title = match versus.result() {
+                    Some(RoundResult::Winner(player)) => format!("Player {} wins!", player + 1),
+                    _ => "Draw".to_string(),
+                };
+                Menu {
+                    title,
+                    lines: versus_summary(versus),
+                    items: GAME_OVER_ITEMS.map(String::from).to_vec(),
+                    selected: *selected,
+                    hint: menu_hint.to_string(),
+                }
+            }
+            Screen::GameOver { name, selected } => {
+                let title = if self.game.is_won() {
+                    "Board cleared - you win!"
+                } else {
+                    "Game over"
+                };
+                let mut lines = self.summary(self.best());
+                let (items, hint) = match name {
+                    Some(name) => {
+                        lines.push(String::new());
+                        lines.push("New high score! Enter your name:".to_string());
+                        lines.push(format!("{}_", name));
+                        (Vec::new(), "Enter to save, Esc to skip")
+                    }
+                    None => (GAME_OVER_ITEMS.map(String::from).to_vec(), menu_hint),
+                };
+                Menu {
+                    title: title.to_string(),
+                    lines,
+                    items,
+                    selected: *selected,
+                    hint: hint.to_string(),
+                }
+            }
+        };
+        Some(menu)
+    }
+
+    /// A line for the bottom of the board, if there is something to say:
+    /// the last error, or which autopilot is playing.
+    pub fn status(&self) -> Option<String> {
+        if self.error.is_some() {
+            return self.error.clone();
+        }
+        let (_, bot) = self.autopilot.as_ref()?;
+        (self.screen == Screen::Playing && self.versus.is_none()).then(|| {
+            format!(
+                "Autopilot ({}) - press {:?} to switch",
+                bot.name(),
+                self.config.keys.autopilot.0
+            )
+        })
+    }
+
+    /// How the finished game went, for the game-over screen.
+    fn summary(&self, best: u32) -> Vec<String> {
+        let stats = self.game.stats();
+        let played = stats.time_played.as_secs();
+        vec![
+            format!("Score {} (best {})", self.game.score(), best),
+            format!("Length {}", self.game.snake().len()),
+            format!("Time played {}:{:02}", played / 60, played % 60),
+            format!(
+                "Food eaten {}, turns {}",
+                stats.food_eaten,
+                stats.total_turns()
+            ),
+        ]
+    }
+}
+
+/// How each player did in a finished versus round, for the game-over screen.
+pub fn versus_summary(versus: &Versus) -> Vec<String> {
+    let mut lines: Vec<String> = versus
+        .players()
+        .iter()
+        .enumerate()
+        .map(|(i, player)| {
+            let outcome = if player.is_alive() { "" } else { ", crashed" };
+            format!(
+                "Player {}: score {}, length {}, turns {}{}",
+                i + 1,
+                player.score(),
+                player.snake().len(),
+                player.stats().total_turns(),
+                outcome
+            )
+        })
+        .collect();
+    let played = versus.players()[0].stats().time_played.as_secs();
+    lines.push(format!("Time played {}:{:02}", played / 60, played % 60));
+    lines
+}
+
+fn new_game(settings: GameSettings, seed: Option<u64>) -> Game {
+    Game::with_settings(settings, seed.unwrap_or_else(rand::random))
+}
+
+/// The autopilot after the one at `current` in [`BotKind::ALL`], or `None`
+/// once the list runs out and control goes back to the player. A bot that
+/// cannot play the board is skipped.
+fn next_autopilot(
+    current: Option<usize>,
+    settings: &GameSettings,
+) -> Option<(usize, Box<dyn Bot>)> {
+    let start = current.map_or(0, |i| i + 1);
+    (start..BotKind::ALL.len()).find_map(|i| BotKind::ALL[i].build(settings).map(|bot| (i, bot)))
+}
+
+fn up(selected: usize) -> usize {
+    selected.saturating_sub(1)
+}
+
+fn down(selected: usize, len: usize) -> usize {
+    (selected + 1).min(len - 1)
+}
+
+/// The value `step` places after `current` in `values`, wrapping around.
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: isize) -> T {
+    let i = values.iter().position(|v| *v == current).unwrap_or(0) as isize;
+    values[(i + step).rem_euclid(values.len() as isize) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_snake_game::DEFAULT_GRID_SIZE;
+
+    fn app() -> App {
+        App::new(Config::default(), Some(1), false)
+    }
+
+    fn press(app: &mut App, keys: &[Key]) {
+        for key in keys {
+            app.on_key(*key);
+        }
+    }
+
+    #[test]
+    fn title_leads_into_a_game_and_the_pause_menu_back_out() {
+        let mut app = app();
+        assert_eq!(app.screen, Screen::Title { selected: 0 });
+
+        press(&mut app, &[Key::Return]);
+        assert_eq!(
+            app.screen,
+            Screen::NewGame {
+                selected: NEW_GAME_START,
+                versus: false
+            }
+        );
+        press(&mut app, &[Key::Return]);
+        assert_eq!(app.screen, Screen::Playing);
+        assert!(app.menu().is_none());
+
+        press(&mut app, &[Key::P]);
+        assert_eq!(app.screen, Screen::Paused { selected: 0 });
+        assert!(app.game().is_paused());
+        press(&mut app, &[Key::Escape]);
+        assert_eq!(app.screen, Screen::Playing);
+        assert!(!app.game().is_paused());
+
+        press(&mut app, &[Key::Escape, Key::Down, Key::Down, Key::Return]);
+        assert_eq!(app.screen, Screen::Title { selected: 0 });
+    }
+
+    #[test]
+    fn settings_change_the_next_game() {
+        let mut app = app();
+        press(&mut app, &[Key::Down, Key::Down, Key::Return]);
+        assert_eq!(app.screen, Screen::Settings { selected: 0 });
+
+        press(&mut app, &[Key::Right, Key::Right, Key::Down, Key::Left]);
+        assert_eq!(app.game().settings().width, DEFAULT_GRID_SIZE + 2);
+        assert_eq!(app.game().settings().height, DEFAULT_GRID_SIZE - 1);
+
+        press(&mut app, &[Key::Escape]);
+        assert_eq!(app.screen, Screen::Title { selected: 2 });
+        press(&mut app, &[Key::Up, Key::Up, Key::Return, Key::Return]);
+        assert_eq!(app.screen, Screen::Playing);
+        assert_eq!(app.game().settings().width, DEFAULT_GRID_SIZE + 2);
+    }
+
+    #[test]
+    fn high_scores_go_back_where_they_were_opened() {
+        let mut app = app();
+        press(&mut app, &[Key::Down, Key::H]);
+        assert!(matches!(app.screen, Screen::HighScores { .. }));
+        press(&mut app, &[Key::Escape]);
+        assert_eq!(app.screen, Screen::Title { selected: 1 });
+    }
+
+    #[test]
+    fn skipping_the_menus_starts_playing() {
+        let app = App::new(Config::default(), Some(1), true);
+        assert_eq!(app.screen, Screen::Playing);
+    }
+}
@@ -0,0 +1,223 @@
+use piston_window::*;
+use rust_snake_game::{
+    BoundaryMode, Game, GameSettings, Position, Versus, CRASHED_ALPHA, GAME_OVER_TINT,
+    ROUND_OVER_TINT, VICTORY_TINT, WALL_COLOR,
+};
+
+use crate::config::DisplayConfig;
+use crate::hud::HUD_HEIGHT;
+
+/// Side of a cell in pixels unless configured otherwise.
+pub const DEFAULT_CELL_SIZE: u32 = 25;
+
+/// Smallest and largest cell side accepted, in pixels.
+pub const MIN_CELL_SIZE: u32 = 2;
+pub const MAX_CELL_SIZE: u32 = 100;
+
+/// Smallest the window gets, so the HUD text and the menus fit around small
+/// boards.
+const MIN_WINDOW_WIDTH: u32 = 400;
+const MIN_WINDOW_HEIGHT: u32 = 400;
+
+/// Largest the window gets. Cells shrink to fit, down to a single pixel,
+/// which still fits the largest board below the HUD.
+const MAX_WINDOW_WIDTH: u32 = 1920;
+const MAX_WINDOW_HEIGHT: u32 = 1080;
+
+/// Where the board sits in the window and how big it is drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub cell_size: u32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Layout {
+    /// Uses the configured cell size unless that would make the window larger
+    /// than [`MAX_WINDOW_WIDTH`] x [`MAX_WINDOW_HEIGHT`].
+    pub fn new(settings: &GameSettings, display: &DisplayConfig) -> Self {
+        let cell_size = display
+            .cell_size
+            .min(MAX_WINDOW_WIDTH / settings.width)
+            .min((MAX_WINDOW_HEIGHT - HUD_HEIGHT) / settings.height)
+            .max(1);
+        Self {
+            cell_size,
+            columns: settings.width,
+            rows: settings.height,
+        }
+    }
+
+    pub fn board_width(&self) -> u32 {
+        self.columns * self.cell_size
+    }
+
+    pub fn board_height(&self) -> u32 {
+        self.rows * self.cell_size
+    }
+
+    /// Window size that fits the HUD strip above the board.
+    pub fn window_size(&self) -> [u32; 2] {
+        [
+            self.board_width().max(MIN_WINDOW_WIDTH),
+            (self.board_height() + HUD_HEIGHT).max(MIN_WINDOW_HEIGHT),
+        ]
+    }
+
+    /// Top left corner of the board, which is centred below the HUD when the
+    /// window is bigger.
+    fn origin(&self) -> [u32; 2] {
+        let [width, height] = self.window_size();
+        [
+            (width - self.board_width()) / 2,
+            HUD_HEIGHT + (height - HUD_HEIGHT - self.board_height()) / 2,
+        ]
+    }
+}
+
+/// Draws the snake, the food and the game-over or victory tint below the HUD
+/// strip.
+pub fn draw(game: &Game, layout: &Layout, display: &DisplayConfig, c: &Context, g: &mut G2d) {
+    let board = board_transform(layout, c);
+
+    // Draw snake
+    for segment in game.snake() {
+        fill_cell(*segment, display.snake.0, layout, board, g);
+    }
+
+    // Draw food, unless the snake has eaten it all
+    if !game.is_won() {
+        fill_cell(game.food(), display.food.0, layout, board, g);
+    }
+
+    draw_walls(game.settings(), layout, c, board, g);
+
+    // Draw game over indicator
+    if game.is_game_over() {
+        tint(GAME_OVER_TINT, layout, board, g);
+    }
+
+    // Draw victory indicator
+    if game.is_won() {
+        tint(VICTORY_TINT, layout, board, g);
+    }
+}
+
+/// Draws both snakes of a versus round and the food, tinting the board once
+/// the round is over. A snake that crashed is drawn faded.
+pub fn draw_versus(
+    versus: &Versus,
+    layout: &Layout,
+    display: &DisplayConfig,
+    c: &Context,
+    g: &mut G2d,
+) {
+    let board = board_transform(layout, c);
+
+    let colors = [display.snake.0, display.player2_snake.0];
+    for (player, mut color) in versus.players().iter().zip(colors) {
+        if !player.is_alive() {
+            color[3] *= CRASHED_ALPHA;
+        }
+        for segment in player.snake() {
+            fill_cell(*segment, color, layout, board, g);
+        }
+    }
+    if !versus.is_finished() {
+        fill_cell(versus.food(), display.food.0, layout, board, g);
+    }
+
+    draw_walls(versus.settings(), layout, c, board, g);
+
+    if versus.is_finished() {
+        tint(ROUND_OVER_TINT, layout, board, g);
+    }
+}
+
+/// Transform with the board's top left corner at the origin.
+fn board_transform(layout: &Layout, c: &Context) -> math::Matrix2d {
+    let [left, top] = layout.origin();
+    c.transform.trans(left as f64, top as f64)
+}
+
+fn fill_cell(
+    position: Position,
+    color: [f32; 4],
+    layout: &Layout,
+    board: math::Matrix2d,
+    g: &mut G2d,
+) {
+    let cell_size = layout.cell_size as f64;
+    rectangle(
+        color,
+        [
+            position.x as f64 * cell_size,
+            position.y as f64 * cell_size,
+            cell_size,
+            cell_size,
+        ],
+        board,
+        g,
+    );
+}
+
+/// Outlines the board when its edges are lethal.
+fn draw_walls(
+    settings: &GameSettings,
+    layout: &Layout,
+    c: &Context,
+    board: math::Matrix2d,
+    g: &mut G2d,
+) {
+    if settings.boundary == BoundaryMode::Walls {
+        let (width, height) = (layout.board_width() as f64, layout.board_height() as f64);
+        Rectangle::new_border(WALL_COLOR, 1.0).draw(
+            [1.0, 1.0, width - 2.0, height - 2.0],
+            &c.draw_state,
+            board,
+            g,
+        );
+    }
+}
+
+/// Covers the whole board in `color`.
+fn tint(color: [f32; 4], layout: &Layout, board: math::Matrix2d, g: &mut G2d) {
+    let (width, height) = (layout.board_width() as f64, layout.board_height() as f64);
+    rectangle(color, [0.0, 0.0, width, height], board, g);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_snake_game::MAX_GRID_SIZE;
+
+    fn layout(width: u32, height: u32, cell_size: u32) -> Layout {
+        let settings = GameSettings {
+            width,
+            height,
+            ..GameSettings::default()
+        };
+        let display = DisplayConfig {
+            cell_size,
+            ..DisplayConfig::default()
+        };
+        Layout::new(&settings, &display)
+    }
+
+    #[test]
+    fn boards_that_fit_keep_the_configured_cell_size() {
+        let layout = layout(20, 20, 25);
+        assert_eq!(layout.cell_size, 25);
+        assert_eq!(layout.window_size(), [500, 540]);
+    }
+
+    #[test]
+    fn cells_shrink_to_keep_the_window_on_screen() {
+        for (width, height) in [(100, 20), (20, 100), (MAX_GRID_SIZE, MAX_GRID_SIZE)] {
+            let [window_width, window_height] = layout(width, height, MAX_CELL_SIZE).window_size();
+            assert!(window_width <= MAX_WINDOW_WIDTH, "{}x{}", width, height);
+            assert!(window_height <= MAX_WINDOW_HEIGHT, "{}x{}", width, height);
+        }
+        assert_eq!(layout(100, 20, MAX_CELL_SIZE).cell_size, 19);
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::game::{Direction, Game, GameSettings, Position};
+
+/// Something that can play a [`Game`] by choosing a turn before every tick.
+pub trait Bot {
+    fn name(&self) -> &'static str;
+
+    /// Picks the turn to take on the next tick, or `None` to keep going.
+    fn next_direction(&mut self, game: &Game) -> Option<Direction>;
+
+    /// Queues [`Bot::next_direction`] on `game`. Pass this to
+    /// [`Game::update_with`] to let the bot play.
+    fn drive(&mut self, game: &mut Game) {
+        if let Some(direction) = self.next_direction(game) {
+            game.change_direction(direction);
+        }
+    }
+}
+
+/// The bots that come with the crate, for picking one by name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BotKind {
+    #[default]
+    Pathfinding,
+    Hamiltonian,
+}
+
+impl BotKind {
+    pub const ALL: [BotKind; 2] = [BotKind::Pathfinding, BotKind::Hamiltonian];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotKind::Pathfinding => "pathfinding",
+            BotKind::Hamiltonian => "hamiltonian",
+        }
+    }
+
+    /// A fresh bot of this kind for games played with `settings`, or `None`
+    /// if it cannot play that board.
+    pub fn build(&self, settings: &GameSettings) -> Option<Box<dyn Bot>> {
+        match self {
+            BotKind::Pathfinding => Some(Box::new(PathfindingBot::new())),
+            BotKind::Hamiltonian => HamiltonianBot::new(settings.width, settings.height)
+                .map(|bot| Box::new(bot) as Box<dyn Bot>),
+        }
+    }
+}
+
+impl fmt::Display for BotKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BotKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pathfinding" => Ok(BotKind::Pathfinding),
+            "hamiltonian" => Ok(BotKind::Hamiltonian),
+            _ => Err(format!(
+                "unknown bot `{}` (expected pathfinding or hamiltonian)",
+                s
+            )),
+        }
+    }
+}
+
+/// Greedy autopilot: takes the shortest path to the food as long as it can
+/// still reach its own tail afterwards, otherwise chases its tail, and as a
+/// last resort moves towards the most open space.
+#[derive(Debug, Default)]
+pub struct PathfindingBot {
+    last_score: u32,
+    hungry_ticks: usize,
+}
+
+impl PathfindingBot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tail chasing can circle forever without the food ever becoming safe
+    /// to reach. After this many ticks without eating, go for it anyway.
+    fn patience(game: &Game) -> usize {
+        game.settings().cell_count() * 2
+    }
+}
+
+impl Bot for PathfindingBot {
+    fn name(&self) -> &'static str {
+        BotKind::Pathfinding.as_str()
+    }
+
+    fn next_direction(&mut self, game: &Game) -> Option<Direction> {
+        let body = game.snake();
+        let heading = game.direction();
+
+        if game.score() != self.last_score || game.tick() == 0 {
+            self.last_score = game.score();
+            self.hungry_ticks = 0;
+        }
+        self.hungry_ticks += 1;
+
+        if let Some(path) = shortest_path(game, body, heading, game.food()) {
+            let after = follow(game, body, &path, true);
+            let heading_after = *path.last()?;
+            if after.len() == game.settings().cell_count()
+                || self.hungry_ticks > Self::patience(game)
+                || tail_reachable(game, &after, heading_after)
+            {
+                return path.first().copied();
+            }
+        }
+
+        if body.len() > 1 {
+            if let Some(path) = shortest_path(game, body, heading, body[body.len() - 1]) {
+                return path.first().copied();
+            }
+        }
+
+        most_open_move(game, body, heading)
+    }
+}
+
+fn index(game: &Game, position: Position) -> usize {
+    (position.y * game.settings().width + position.x) as usize
+}
+
+/// For every cell, how many more ticks the body keeps it occupied. The head
+/// moves first each tick, so a cell is only safe to enter on step `k` if `k`
+/// is greater than this.
+fn occupancy(game: &Game, body: &VecDeque<Position>) -> Vec<usize> {
+    let mut blocked = vec![0; game.settings().cell_count()];
+    for (i, segment) in body.iter().enumerate() {
+        blocked[index(game, *segment)] = body.len() - i;
+    }
+    blocked
+}
+
+/// Breadth-first search from the head of `body` to `goal` that accounts for
+/// the tail moving out of the way. Returns the turns to take, first turn
+/// first.
+fn shortest_path(
+    game: &Game,
+    body: &VecDeque<Position>,
+    heading: Direction,
+    goal: Position,
+) -> Option<Vec<Direction>> {
+    let start = body[0];
+    let blocked = occupancy(game, body);
+    // Cell we came from and the step that got us here
+    let mut came_from: Vec<Option<(Position, Direction)>> =
+        vec![None; game.settings().cell_count()];
+    let mut distance = vec![usize::MAX; game.settings().cell_count()];
+    let mut queue = VecDeque::new();
+
+    distance[index(game, start)] = 0;
+    queue.push_back(start);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == goal && cell != start {
+            break;
+        }
+
+        let steps = distance[index(game, cell)] + 1;
+        for direction in Direction::ALL {
+            if cell == start && direction == heading.opposite() {
+                continue;
+            }
+            let Some(next) = game.neighbor(cell, direction) else {
+                continue;
+            };
+            let i = index(game, next);
+            if distance[i] != usize::MAX || blocked[i] >= steps {
+                continue;
+            }
+            distance[i] = steps;
+            came_from[i] = Some((cell, direction));
+            queue.push_back(next);
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut cell = goal;
+    while let Some((previous, direction)) = came_from[index(game, cell)] {
+        path.push(direction);
+        cell = previous;
+    }
+    if path.is_empty() {
+        return None;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Where `body` ends up after taking `path`, growing on the last step if
+/// `eats` is set.
+fn follow(
+    game: &Game,
+    body: &VecDeque<Position>,
+    path: &[Direction],
+    eats: bool,
+) -> VecDeque<Position> {
+    let mut body = body.clone();
+    for (i, direction) in path.iter().enumerate() {
+        let Some(head) = game.neighbor(body[0], *direction) else {
+            break;
+        };
+        body.push_front(head);
+        if !(eats && i == path.len() - 1) {
+            body.pop_back();
+        }
+    }
+    body
+}
+
+fn tail_reachable(game: &Game, body: &VecDeque<Position>, heading: Direction) -> bool {
+    body.len() < 3 || shortest_path(game, body, heading, body[body.len() - 1]).is_some()
+}
+
+/// The move into the largest connected free area, if any move survives.
+fn most_open_move(game: &Game, body: &VecDeque<Position>, heading: Direction) -> Option<Direction> {
+    let blocked = occupancy(game, body);
+
+    Direction::ALL
+        .into_iter()
+        .filter(|direction| *direction != heading.opposite())
+        .filter_map(|direction| {
+            let next = game.neighbor(body[0], direction)?;
+            if blocked[index(game, next)] >= 1 {
+                return None;
+            }
+            let after = follow(game, body, &[direction], next == game.food());
+            Some((direction, open_area(game, &after)))
+        })
+        .max_by_key(|(_, area)| *area)
+        .map(|(direction, _)| direction)
+}
+
+/// Number of free cells reachable from the head of `body`.
+fn open_area(game: &Game, body: &VecDeque<Position>) -> usize {
+    let mut seen = vec![false; game.settings().cell_count()];
+    for segment in body {
+        seen[index(game, *segment)] = true;
+    }
+
+    let mut area = 0;
+    let mut stack = vec![body[0]];
+    while let Some(cell) = stack.pop() {
+        for direction in Direction::ALL {
+            if let Some(next) = game.neighbor(cell, direction) {
+                if !seen[index(game, next)] {
+                    seen[index(game, next)] = true;
+                    area += 1;
+                    stack.push(next);
+                }
+            }
+        }
+    }
+    area
+}
+
+/// Follows a fixed Hamiltonian cycle over the whole board, which can never
+/// trap the snake, and cuts across it towards the food while the snake is
+/// short enough for the shortcut to be safe. Fills the board.
+#[derive(Debug)]
+pub struct HamiltonianBot {
+    width: u32,
+    /// Position of every cell along the cycle, indexed like the board
+    order: Vec<usize>,
+    fallback: PathfindingBot,
+}
+
+impl HamiltonianBot {
+    /// Shortcuts must leave at least this many cells between the new head and
+    /// the tail along the cycle, so a few meals in a row cannot close the gap.
+    const SHORTCUT_SLACK: usize = 3;
+
+    /// Builds the cycle for a `width` x `height` board. Returns `None` when
+    /// no such cycle exists, which is when both sides are odd.
+    pub fn new(width: u32, height: u32) -> Option<Self> {
+        let cycle = hamiltonian_cycle(width, height)?;
+        let mut order = vec![0; cycle.len()];
+        for (i, cell) in cycle.iter().enumerate() {
+            order[(cell.y * width + cell.x) as usize] = i;
+        }
+
+        Some(Self {
+            width,
+            order,
+            fallback: PathfindingBot::new(),
+        })
+    }
+
+    fn order_of(&self, position: Position) -> usize {
+        self.order[(position.y * self.width + position.x) as usize]
+    }
+
+    /// Whether the body runs forwards along the cycle from tail to head, which
+    /// is what makes following the cycle safe. Not the case right after the
+    /// bot takes over a game in progress.
+    fn on_cycle(&self, body: &VecDeque<Position>) -> bool {
+        let cells = self.order.len();
+        let mut span = 0;
+        for (ahead, behind) in body.iter().zip(body.iter().skip(1)) {
+            let step = (self.order_of(*ahead) + cells - self.order_of(*behind)) % cells;
+            if step == 0 {
+                return false;
+            }
+            span += step;
+        }
+        span < cells
+    }
+}
+
+impl Bot for HamiltonianBot {
+    fn name(&self) -> &'static str {
+        BotKind::Hamiltonian.as_str()
+    }
+
+    fn next_direction(&mut self, game: &Game) -> Option<Direction> {
+        let body = game.snake();
+        let heading = game.direction();
+        let cells = self.order.len();
+        // Steps along the cycle from `from` to `to`
+        let distance = |from: usize, to: usize| (to + cells - from) % cells;
+
+        if !self.on_cycle(body) {
+            // Get onto the cycle by following it whenever that is safe
+            let head = self.order_of(body[0]);
+            let onto_cycle = Direction::ALL.into_iter().find(|direction| {
+                game.neighbor(body[0], *direction).is_some_and(|next| {
+                    distance(head, self.order_of(next)) == 1
+                        && !game.is_occupied(next)
+                        && *direction != heading.opposite()
+                        && tail_reachable(
+                            game,
+                            &follow(game, body, &[*direction], next == game.food()),
+                            *direction,
+                        )
+                })
+            });
+            return onto_cycle.or_else(|| self.fallback.next_direction(game));
+        }
+
+        let head = self.order_of(body[0]);
+        let to_tail = match body.len() {
+            1 => cells,
+            _ => distance(head, self.order_of(body[body.len() - 1])),
+        };
+        let to_food = distance(head, self.order_of(game.food()));
+        let shortcuts = body.len() * 2 < cells;
+
+        let mut best: Option<(Direction, usize)> = None;
+        for direction in Direction::ALL {
+            if direction == heading.opposite() {
+                continue;
+            }
+            let Some(next) = game.neighbor(body[0], direction) else {
+                continue;
+            };
+            if game.is_occupied(next) {
+                continue;
+            }
+
+            let ahead = distance(head, self.order_of(next));
+            let safe = if ahead == 1 {
+                ahead < to_tail
+            } else {
+                shortcuts && ahead <= to_food && ahead + Self::SHORTCUT_SLACK < to_tail
+            };
+            if safe && best.is_none_or(|(_, furthest)| ahead > furthest) {
+                best = Some((direction, ahead));
+            }
+        }
+
+        match best {
+            Some((direction, _)) => Some(direction),
+            None => self.fallback.next_direction(game),
+        }
+    }
+}
+
+/// A closed tour of every cell on a `width` x `height` board that only uses
+/// moves between adjacent cells. Sweeps back and forth across all columns but
+/// the first, then returns up the first column.
+fn hamiltonian_cycle(width: u32, height: u32) -> Option<Vec<Position>> {
+    if width < 2 || height < 2 || (width % 2 == 1 && height % 2 == 1) {
+        return None;
+    }
+    if height % 2 == 1 {
+        // Sweep the other way round and mirror the result
+        let transposed = hamiltonian_cycle(height, width)?;
+        return Some(
+            transposed
+                .into_iter()
+                .map(|p| Position { x: p.y, y: p.x })
+                .collect(),
+        );
+    }
+
+    let mut cycle = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        if y % 2 == 0 {
+            cycle.extend((1..width).map(|x| Position { x, y }));
+        } else {
+            cycle.extend((1..width).rev().map(|x| Position { x, y }));
+        }
+    }
+    cycle.extend((0..height).rev().map(|y| Position { x: 0, y }));
+
+    Some(cycle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::BoundaryMode;
+
+    fn settings(width: u32, height: u32) -> GameSettings {
+        GameSettings {
+            width,
+            height,
+            boundary: BoundaryMode::Walls,
+            ..GameSettings::default()
+        }
+    }
+
+    fn cells(cells: &[(u32, u32)]) -> Vec<Position> {
+        cells.iter().map(|&(x, y)| Position { x, y }).collect()
+    }
+
+    #[test]
+    fn pathfinding_goes_through_cells_the_tail_leaves_on_the_way() {
+        // Row 1 is all snake, cutting the food off until the tail moves on
+        let snake = cells(&[(0, 2), (0, 1), (1, 1), (2, 1), (3, 1)]);
+        let food = Position { x: 3, y: 0 };
+        let game = Game::with_snake(settings(4, 4), &snake, Direction::Down, food);
+
+        let (up, right) = (Direction::Up, Direction::Right);
+        assert_eq!(
+            shortest_path(&game, game.snake(), Direction::Down, food),
+            Some(vec![right, right, up, up, right])
+        );
+        assert_eq!(PathfindingBot::new().next_direction(&game), Some(right));
+    }
+
+    #[test]
+    fn pathfinding_chases_its_tail_rather_than_eat_itself_into_a_corner() {
+        // The food sits in a corner the snake could not get back out of
+        let snake = cells(&[(1, 0), (1, 1), (0, 1), (0, 2)]);
+        let food = Position { x: 0, y: 0 };
+        let game = Game::with_snake(settings(4, 4), &snake, Direction::Up, food);
+
+        assert_eq!(
+            shortest_path(&game, game.snake(), Direction::Up, food),
+            Some(vec![Direction::Left])
+        );
+        assert_eq!(
+            PathfindingBot::new().next_direction(&game),
+            Some(Direction::Right)
+        );
+    }
+
+    #[test]
+    fn pathfinding_heads_for_open_space_when_the_tail_is_out_of_reach() {
+        // Three free cells along the top row, the food in the one to the left
+        let snake = cells(&[
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+            (0, 3),
+        ]);
+        let game = Game::with_snake(
+            settings(4, 4),
+            &snake,
+            Direction::Up,
+            Position { x: 0, y: 0 },
+        );
+        let body = game.snake();
+
+        assert_eq!(shortest_path(&game, body, Direction::Up, body[12]), None);
+        assert_eq!(
+            PathfindingBot::new().next_direction(&game),
+            Some(Direction::Right)
+        );
+    }
+
+    #[test]
+    fn hamiltonian_cycle_visits_every_cell_once_and_closes() {
+        for (width, height) in [(6, 5), (5, 6), (4, 7)] {
+            let cycle = hamiltonian_cycle(width, height).unwrap();
+            assert_eq!(cycle.len(), (width * height) as usize);
+
+            let mut seen = vec![false; cycle.len()];
+            for (i, cell) in cycle.iter().enumerate() {
+                assert!(cell.x < width && cell.y < height);
+                let index = (cell.y * width + cell.x) as usize;
+                assert!(!seen[index], "{:?} visited twice", cell);
+                seen[index] = true;
+
+                let next = cycle[(i + 1) % cycle.len()];
+                assert_eq!(cell.x.abs_diff(next.x) + cell.y.abs_diff(next.y), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn hamiltonian_cycle_needs_an_even_side() {
+        assert!(hamiltonian_cycle(5, 5).is_none());
+        assert!(HamiltonianBot::new(5, 5).is_none());
+    }
+
+    #[test]
+    fn hamiltonian_shortcuts_keep_clear_of_the_tail() {
+        let settings = settings(6, 6);
+        for seed in 0..5 {
+            let mut game = Game::with_settings(settings, seed);
+            let mut bot = HamiltonianBot::new(settings.width, settings.height).unwrap();
+            let cells = bot.order.len();
+
+            while !game.is_finished() {
+                let body = game.snake();
+                let head = bot.order_of(body[0]);
+                let direction = bot.next_direction(&game).unwrap_or(game.direction());
+                let next = game.neighbor(body[0], direction).unwrap();
+                let ahead = (bot.order_of(next) + cells - head) % cells;
+                if ahead > 1 && body.len() > 1 {
+                    let tail = bot.order_of(body[body.len() - 1]);
+                    let to_tail = (tail + cells - head) % cells;
+                    assert!(ahead + HamiltonianBot::SHORTCUT_SLACK < to_tail);
+                }
+
+                game.change_direction(direction);
+                game.step();
+            }
+            assert!(
+                game.is_won(),
+                "seed {} lost with score {}",
+                seed,
+                game.score()
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use rust_snake_game::{
+    BotKind, BoundaryMode, Difficulty, StatsFormat, DEFAULT_PORT, MAX_GRID_SIZE, MIN_GRID_SIZE,
+};
+use std::path::PathBuf;
+
+use crate::board::{MAX_CELL_SIZE, MIN_CELL_SIZE};
+
+/// Running without a subcommand starts a game, same as `play`.
+#[derive(Parser)]
+#[command(version, about = "A simple snake game built with Piston")]
+pub struct Cli {
+    /// Config file to read [default: <config dir>/rust-snake-game/config.toml]
+    #[arg(long, value_name = "FILE", env = "SNAKE_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub play: PlayArgs,
+}
+
+impl Cli {
+    /// Parses the process arguments, exiting with clap's message on an error.
+    pub fn parse_args() -> Self {
+        Self::try_parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+
+    /// Parses `args` like `try_parse_from`, but also refuses the play options
+    /// before a subcommand, which would otherwise be ignored. Global options
+    /// such as `--config` may go on either side of the subcommand.
+    pub fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let mut command = Self::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+        if let Some((name, _)) = matches.subcommand() {
+            let play_arg = command.get_arguments().find(|arg| {
+                !arg.is_global_set()
+                    && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            });
+            if let Some(arg) = play_arg {
+                let message = format!(
+                    "the argument '--{}' cannot be used with the subcommand '{}'",
+                    arg.get_long().unwrap_or(arg.get_id().as_str()),
+                    name
+                );
+                return Err(command.error(ErrorKind::ArgumentConflict, message));
+            }
+        }
+        Self::from_arg_matches(&matches).map_err(|e| e.format(&mut command))
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Play a game in a window
+    Play(PlayArgs),
+    /// Watch a saved replay
+    Replay(ReplayArgs),
+    /// Summarise the games saved in the stats directory
+    Stats(StatsArgs),
+    /// Let a bot play games without a window and print how they went
+    Bot(BotArgs),
+    /// Measure how many ticks per second the engine runs without a window
+    Bench(BenchArgs),
+    /// Host a versus round for a player on another machine
+    Host(HostArgs),
+    /// Join a versus round hosted on another machine
+    Join(JoinArgs),
+    /// Watch a versus round hosted on another machine
+    Spectate(SpectateArgs),
+    /// Turn a replay into PNG frames or an animated GIF without a window
+    Render(RenderArgs),
+}
+
+/// Board options shared by every command that starts games.
+#[derive(Args, Clone, Default)]
+pub struct BoardArgs {
+    /// What happens at the edge of the board: wrap or walls [default: wrap]
+    #[arg(long)]
+    pub mode: Option<BoundaryMode>,
+
+    /// Board width in cells [default: 20]
+    #[arg(long, value_parser = grid_size())]
+    pub width: Option<u32>,
+
+    /// Board height in cells [default: 20]
+    #[arg(long, value_parser = grid_size())]
+    pub height: Option<u32>,
+
+    /// Speed preset: easy, normal, hard or insane [default: normal]
+    #[arg(long)]
+    pub difficulty: Option<Difficulty>,
+}
+
+impl BoardArgs {
+    /// Whether the command line set any of the board options.
+    pub fn is_set(&self) -> bool {
+        self.mode.is_some()
+            || self.width.is_some()
+            || self.height.is_some()
+            || self.difficulty.is_some()
+    }
+}
+
+#[derive(Args, Clone, Default)]
+pub struct PlayArgs {
+    /// Seed for food placement; the same seed and inputs replay the same game
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub board: BoardArgs,
+
+    /// Side of a cell in pixels; the window is sized to fit the board [default: 25]
+    #[arg(long, value_parser = cell_size())]
+    pub cell_size: Option<u32>,
+
+    /// Format finished games are saved in: text, json or csv [default: text]
+    #[arg(long)]
+    pub stats_format: Option<StatsFormat>,
+
+    /// Directory stats files are written to [default: <data dir>/rust-snake-game/stats]
+    #[arg(long, env = "SNAKE_STATS_DIR")]
+    pub stats_dir: Option<PathBuf>,
+
+    /// Draw in the terminal instead of opening a window
+    #[arg(long)]
+    pub tui: bool,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// Replay file to play back
+    pub file: PathBuf,
+
+    /// Side of a cell in pixels [default: 25]
+    #[arg(long, value_parser = cell_size())]
+    pub cell_size: Option<u32>,
+
+    /// Draw in the terminal instead of opening a window
+    #[arg(long)]
+    pub tui: bool,
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Replay file to render
+    pub file: PathBuf,
+
+    /// Directory to write numbered PNG frames to, or a file ending in .gif
+    #[arg(long, short)]
+    pub output: PathBuf,
+
+    /// Side of a cell in pixels [default: 25]
+    #[arg(long, value_parser = cell_size())]
+    pub cell_size: Option<u32>,
+
+    /// How fast the GIF plays compared with the game; 2 is twice as fast
+    #[arg(long, default_value_t = 1.0)]
+    pub rate: f64,
+
+    /// Stop after this many ticks, for replays that never end
+    #[arg(long, default_value_t = 100_000)]
+    pub max_ticks: u64,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Directory to read stats files from [default: <data dir>/rust-snake-game/stats]
+    #[arg(long, env = "SNAKE_STATS_DIR")]
+    pub stats_dir: Option<PathBuf>,
+
+    /// Print the summary as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct BotArgs {
+    /// Bot to play with: pathfinding or hamiltonian
+    #[arg(long, default_value_t = BotKind::Pathfinding)]
+    pub bot: BotKind,
+
+    /// Number of games to play
+    #[arg(long, default_value_t = 1)]
+    pub games: u32,
+
+    /// Seed of the first game; later games use the following seeds [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Give up on a game after this many ticks
+    #[arg(long, default_value_t = 1_000_000)]
+    pub max_ticks: u64,
+
+    #[command(flatten)]
+    pub board: BoardArgs,
+
+    /// Print one JSON object per game instead of a line of text
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Ticks to run in total, starting new games as they finish
+    #[arg(long, default_value_t = 1_000_000)]
+    pub ticks: u64,
+
+    /// Let a bot steer, timing it along with the engine [default: random turns]
+    #[arg(long)]
+    pub bot: Option<BotKind>,
+
+    /// Seed of the first game [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub board: BoardArgs,
+}
+
+#[derive(Args)]
+pub struct HostArgs {
+    /// TCP port to listen on
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+
+    /// Seed for food placement [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub board: BoardArgs,
+
+    /// Draw in the terminal instead of opening a window
+    #[arg(long)]
+    pub tui: bool,
+}
+
+#[derive(Args)]
+pub struct JoinArgs {
+    /// Host to join, as `host` or `host:port` (IPv6 addresses in brackets)
+    pub address: String,
+
+    /// Draw in the terminal instead of opening a window
+    #[arg(long)]
+    pub tui: bool,
+}
+
+#[derive(Args)]
+pub struct SpectateArgs {
+    /// Host to watch, as `host` or `host:port` (IPv6 addresses in brackets)
+    pub address: String,
+
+    /// Draw in the terminal instead of opening a window
+    #[arg(long)]
+    pub tui: bool,
+}
+
+fn grid_size() -> clap::builder::RangedI64ValueParser<u32> {
+    clap::value_parser!(u32).range(MIN_GRID_SIZE as i64..=MAX_GRID_SIZE as i64)
+}
+
+fn cell_size() -> clap::builder::RangedI64ValueParser<u32> {
+    clap::value_parser!(u32).range(MIN_CELL_SIZE as i64..=MAX_CELL_SIZE as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_goes_before_or_after_a_subcommand() {
+        for args in [
+            ["rust-snake-game", "--config", "/tmp/c.toml", "bot"],
+            ["rust-snake-game", "bot", "--config", "/tmp/c.toml"],
+        ] {
+            let cli = Cli::try_parse_args(args).unwrap();
+            assert_eq!(cli.config, Some(PathBuf::from("/tmp/c.toml")));
+            assert!(matches!(cli.command, Some(Command::Bot(_))));
+        }
+    }
+
+    #[test]
+    fn play_options_are_refused_before_a_subcommand() {
+        let error = Cli::try_parse_args(["rust-snake-game", "--seed", "3", "bot"])
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        assert!(error.to_string().contains("--seed"));
+    }
+
+    #[test]
+    fn play_options_work_without_a_subcommand() {
+        let cli = Cli::try_parse_args(["rust-snake-game", "--seed", "3", "--tui"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.play.seed, Some(3));
+        assert!(cli.play.tui);
+    }
+
+    #[test]
+    fn any_board_option_counts_as_setting_up_the_board() {
+        let cli = Cli::try_parse_args(["rust-snake-game", "--seed", "3"]).unwrap();
+        assert!(!cli.play.board.is_set());
+        for flag in [
+            ["--mode", "walls"],
+            ["--width", "30"],
+            ["--difficulty", "hard"],
+        ] {
+            let cli = Cli::try_parse_args(["rust-snake-game", flag[0], flag[1]]).unwrap();
+            assert!(cli.play.board.is_set(), "{}", flag[0]);
+        }
+    }
+}
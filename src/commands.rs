@@ -0,0 +1,261 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageResult};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use rust_snake_game::{
+    render_game, Direction, Game, GameSettings, RenderStyle, Replay, ReplayPlayer, StatsHistory,
+};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
+
+use crate::cli::{BenchArgs, BotArgs, RenderArgs, StatsArgs};
+
+/// Prints totals over the games saved in `dir`.
+pub fn stats(args: &StatsArgs, dir: &Path) {
+    let history = StatsHistory::load(dir).unwrap_or_else(|e| {
+        eprintln!("Error reading stats from {}: {}", dir.display(), e);
+        process::exit(1);
+    });
+    let summary = history.summary();
+
+    if args.json {
+        match serde_json::to_string_pretty(&summary) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error writing summary: {}", e),
+        }
+    } else {
+        println!("Stats from {}", dir.display());
+        println!("{}", summary);
+    }
+    if history.skipped > 0 {
+        eprintln!(
+            "Skipped {} text report(s) or unreadable file(s); only games saved with \
+             --stats-format json or csv are counted",
+            history.skipped
+        );
+    }
+}
+
+/// How a game played by [`bot`] went.
+#[derive(Serialize)]
+struct BotGame {
+    seed: u64,
+    score: u32,
+    length: usize,
+    ticks: u64,
+    result: &'static str,
+}
+
+/// Plays `args.games` games with a bot, one after the other, and prints one
+/// line per game followed by the average score.
+pub fn bot(args: &BotArgs, settings: GameSettings) {
+    let first_seed = args.seed.unwrap_or_else(rand::random);
+    let mut total_score = 0u64;
+    let mut cleared = 0;
+
+    for i in 0..args.games {
+        let seed = first_seed.wrapping_add(u64::from(i));
+        let Some(mut bot) = args.bot.build(&settings) else {
+            eprintln!(
+                "Error: the {} bot cannot play a {}x{} board",
+                args.bot, settings.width, settings.height
+            );
+            process::exit(1);
+        };
+
+        let mut game = Game::with_settings(settings, seed);
+        while !game.is_finished() && game.tick() < args.max_ticks {
+            bot.drive(&mut game);
+            game.step();
+        }
+
+        let result = if game.is_won() {
+            cleared += 1;
+            "board cleared"
+        } else if game.is_game_over() {
+            "game over"
+        } else {
+            "stopped"
+        };
+        total_score += u64::from(game.score());
+        let record = BotGame {
+            seed,
+            score: game.score(),
+            length: game.snake().len(),
+            ticks: game.tick(),
+            result,
+        };
+
+        if args.json {
+            match serde_json::to_string(&record) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Error writing game: {}", e),
+            }
+        } else {
+            println!(
+                "seed {}: score {}, length {}, {} ticks, {}",
+                record.seed, record.score, record.length, record.ticks, record.result
+            );
+        }
+    }
+
+    if !args.json && args.games > 1 {
+        println!(
+            "{} games with the {} bot: average score {:.1}, {} board(s) cleared",
+            args.games,
+            args.bot,
+            total_score as f64 / f64::from(args.games),
+            cleared
+        );
+    }
+}
+
+/// Chance of a random turn on each tick when no bot is steering.
+const RANDOM_TURN_CHANCE: f64 = 0.125;
+
+/// Steps games back to back for `args.ticks` ticks and prints the rate.
+pub fn bench(args: &BenchArgs, settings: GameSettings) {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut bot = args.bot.map(|kind| {
+        kind.build(&settings).unwrap_or_else(|| {
+            eprintln!(
+                "Error: the {} bot cannot play a {}x{} board",
+                kind, settings.width, settings.height
+            );
+            process::exit(1);
+        })
+    });
+
+    let mut game = Game::with_settings(settings, seed);
+    let mut games = 1;
+    let start = Instant::now();
+    for _ in 0..args.ticks {
+        if game.is_finished() {
+            game = Game::with_settings(settings, rng.random());
+            if let Some(kind) = args.bot {
+                bot = kind.build(&settings);
+            }
+            games += 1;
+        }
+
+        match bot.as_mut() {
+            Some(bot) => bot.drive(&mut game),
+            None if rng.random_bool(RANDOM_TURN_CHANCE) => {
+                game.change_direction(*Direction::ALL.choose(&mut rng).unwrap())
+            }
+            None => {}
+        }
+        game.step();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let driver = args.bot.map_or("random turns".to_string(), |kind| {
+        format!("the {} bot", kind)
+    });
+    println!(
+        "{} ticks over {} game(s) with {} on a {}x{} board in {:.3}s ({:.0} ticks/s)",
+        args.ticks,
+        games,
+        driver,
+        settings.width,
+        settings.height,
+        elapsed,
+        args.ticks as f64 / elapsed.max(f64::EPSILON)
+    );
+}
+
+/// How long the last frame of a GIF stays up before it loops.
+const GIF_HOLD_LAST_FRAME: Duration = Duration::from_secs(2);
+
+/// Trades GIF encoding time for palette quality, from 1 (best) to 30.
+const GIF_SPEED: i32 = 10;
+
+/// Renders every tick of a replay to numbered PNG files in a directory, or
+/// to an animated GIF that plays at the game's own speed.
+pub fn render(args: &RenderArgs, style: RenderStyle) {
+    if !(args.rate.is_finite() && args.rate > 0.0) {
+        eprintln!("Error: --rate must be a positive number");
+        process::exit(1);
+    }
+    let replay = Replay::load(&args.file).unwrap_or_else(|e| {
+        eprintln!("Error loading replay {}: {}", args.file.display(), e);
+        process::exit(1);
+    });
+    // Checked up front, as a frame that big would not fit in memory
+    if let Err(e) = style.image_size(&replay.settings) {
+        eprintln!("Error: {}; try a smaller --cell-size", e);
+        process::exit(1);
+    }
+
+    let output = &args.output;
+    let is_gif = output
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    let result = if is_gif {
+        render_gif(replay, &style, args)
+    } else {
+        render_pngs(replay, &style, args)
+    };
+    match result {
+        Ok(frames) => println!("Wrote {} frames to {}", frames, output.display()),
+        Err(e) => {
+            eprintln!("Error rendering to {}: {}", output.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+fn render_pngs(replay: Replay, style: &RenderStyle, args: &RenderArgs) -> ImageResult<u64> {
+    fs::create_dir_all(&args.output)?;
+    play_out(replay, args.max_ticks, |game, frame| {
+        let path = args.output.join(format!("frame_{:05}.png", frame));
+        render_game(game, style).save(path)
+    })
+}
+
+fn render_gif(replay: Replay, style: &RenderStyle, args: &RenderArgs) -> ImageResult<u64> {
+    let file = BufWriter::new(File::create(&args.output)?);
+    let mut encoder = GifEncoder::new_with_speed(file, GIF_SPEED);
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    // GIF delays are in hundredths of a second, so round the running total
+    // rather than each delay to keep the whole GIF in time with the game
+    let mut elapsed = 0.0;
+    let mut shown = 0;
+    play_out(replay, args.max_ticks, |game, _| {
+        elapsed += if game.is_finished() {
+            GIF_HOLD_LAST_FRAME.as_secs_f64()
+        } else {
+            game.speed() / args.rate
+        };
+        let until = (elapsed * 100.0).round() as u32;
+        let delay = Delay::from_numer_denom_ms((until - shown) * 10, 1);
+        shown = until;
+        encoder.encode_frame(Frame::from_parts(render_game(game, style), 0, 0, delay))
+    })
+}
+
+/// Calls `frame` with the board before the first tick and after each tick
+/// until the game ends or `max_ticks` have run, along with the frame's
+/// number. Returns how many frames there were.
+fn play_out(
+    replay: Replay,
+    max_ticks: u64,
+    mut frame: impl FnMut(&Game, u64) -> ImageResult<()>,
+) -> ImageResult<u64> {
+    let mut player = ReplayPlayer::new(replay);
+    let mut frames = 0;
+    loop {
+        frame(player.game(), frames)?;
+        frames += 1;
+        if player.is_finished() || player.game().tick() >= max_ticks {
+            return Ok(frames);
+        }
+        player.step();
+    }
+}
@@ -0,0 +1,452 @@
+use piston_window::Key;
+use rust_snake_game::{
+    paths, BoundaryMode, Difficulty, Direction, GameSettings, RenderStyle, SpeedCurve, StatsFormat,
+    DEFAULT_GRID_SIZE,
+};
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::board::{DEFAULT_CELL_SIZE, MAX_CELL_SIZE, MIN_CELL_SIZE};
+use crate::cli::{BoardArgs, PlayArgs};
+
+/// Settings read from `config.toml`. Every value has a default, so the file
+/// only needs to list the ones being changed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub game: GameConfig,
+    /// A custom speed curve in place of a difficulty preset
+    pub speed: Option<SpeedCurve>,
+    pub display: DisplayConfig,
+    pub keys: KeyBindings,
+    pub stats: StatsConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub mode: BoundaryMode,
+    pub width: u32,
+    pub height: u32,
+    /// Defaults to normal, or custom when there is a [speed] section
+    pub difficulty: Option<Difficulty>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            mode: BoundaryMode::default(),
+            width: DEFAULT_GRID_SIZE,
+            height: DEFAULT_GRID_SIZE,
+            difficulty: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Side of a cell in pixels
+    pub cell_size: u32,
+    pub background: Color,
+    pub snake: Color,
+    pub food: Color,
+    /// The second snake in versus rounds
+    pub player2_snake: Color,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: DEFAULT_CELL_SIZE,
+            background: Color([0.0, 0.0, 0.0, 1.0]),
+            snake: Color([0.0, 1.0, 0.0, 1.0]),
+            food: Color([1.0, 0.0, 0.0, 1.0]),
+            player2_snake: Color([0.2, 0.6, 1.0, 1.0]),
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// The cell size and colours for rendering frames without a window.
+    pub fn render_style(&self) -> RenderStyle {
+        RenderStyle {
+            cell_size: self.cell_size,
+            background: self.background.0,
+            snake: self.snake.0,
+            food: self.food.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    /// Defaults to the `stats` folder in the data directory
+    pub dir: Option<PathBuf>,
+    pub format: StatsFormat,
+}
+
+/// A colour written as `#rrggbb` or `#rrggbbaa`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub [f32; 4]);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid colour `{}` (expected #rrggbb or #rrggbbaa)", value);
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut color = [1.0; 4];
+        for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+            let byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+            *channel = byte as f32 / 255.0;
+        }
+        Ok(Color(color))
+    }
+}
+
+/// A key written by name, e.g. `Up`, `W`, `Space` or `F1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyBinding(pub Key);
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let deserializer: StrDeserializer<ValueError> = value.as_str().into_deserializer();
+        match Key::deserialize(deserializer) {
+            Ok(Key::Unknown) | Err(_) => Err(format!(
+                "unknown key `{}` (expected a key name such as Up, W, Space or F1)",
+                value
+            )),
+            Ok(key) => Ok(KeyBinding(key)),
+        }
+    }
+}
+
+/// Something a key can be bound to while playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Turn(Direction),
+    Autopilot,
+    Restart,
+    HighScores,
+    Pause,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub up: KeyBinding,
+    pub down: KeyBinding,
+    pub left: KeyBinding,
+    pub right: KeyBinding,
+    pub autopilot: KeyBinding,
+    pub restart: KeyBinding,
+    pub high_scores: KeyBinding,
+    pub pause: KeyBinding,
+    /// Turn keys of the second player in versus rounds, where the keys above
+    /// steer the first
+    pub player2_up: KeyBinding,
+    pub player2_down: KeyBinding,
+    pub player2_left: KeyBinding,
+    pub player2_right: KeyBinding,
+}
+
+impl KeyBindings {
+    fn all(&self) -> [(&'static str, KeyBinding, Action); 8] {
+        [
+            ("up", self.up, Action::Turn(Direction::Up)),
+            ("down", self.down, Action::Turn(Direction::Down)),
+            ("left", self.left, Action::Turn(Direction::Left)),
+            ("right", self.right, Action::Turn(Direction::Right)),
+            ("autopilot", self.autopilot, Action::Autopilot),
+            ("restart", self.restart, Action::Restart),
+            ("high_scores", self.high_scores, Action::HighScores),
+            ("pause", self.pause, Action::Pause),
+        ]
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.all()
+            .into_iter()
+            .find(|(_, binding, _)| binding.0 == key)
+            .map(|(_, _, action)| action)
+    }
+
+    fn player2(&self) -> [(&'static str, KeyBinding, Direction); 4] {
+        [
+            ("player2_up", self.player2_up, Direction::Up),
+            ("player2_down", self.player2_down, Direction::Down),
+            ("player2_left", self.player2_left, Direction::Left),
+            ("player2_right", self.player2_right, Direction::Right),
+        ]
+    }
+
+    /// The player and turn bound to `key` in a versus round, if any.
+    pub fn versus_turn(&self, key: Key) -> Option<(usize, Direction)> {
+        if let Some((_, _, direction)) = self.player2().into_iter().find(|(_, b, _)| b.0 == key) {
+            return Some((1, direction));
+        }
+        match self.action(key) {
+            Some(Action::Turn(direction)) => Some((0, direction)),
+            _ => None,
+        }
+    }
+
+    /// Checks no key is bound to two actions. The second player's keys may
+    /// share the autopilot key, since versus rounds have no autopilot.
+    fn validate(&self) -> Result<(), String> {
+        let all = self.all();
+        for (i, (name, binding, _)) in all.iter().enumerate() {
+            if let Some((other, _, _)) = all[..i].iter().find(|(_, b, _)| b == binding) {
+                return Err(format!(
+                    "key {:?} is bound to both `{}` and `{}`",
+                    binding.0, other, name
+                ));
+            }
+        }
+
+        let player2 = self.player2();
+        let others = all
+            .iter()
+            .filter(|(_, _, action)| *action != Action::Autopilot)
+            .map(|(name, binding, _)| (*name, *binding));
+        for (i, (name, binding, _)) in player2.iter().enumerate() {
+            let earlier = player2[..i].iter().map(|(name, b, _)| (*name, *b));
+            if let Some((other, _)) = others.clone().chain(earlier).find(|(_, b)| b == binding) {
+                return Err(format!(
+                    "key {:?} is bound to both `{}` and `{}`",
+                    binding.0, other, name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: KeyBinding(Key::Up),
+            down: KeyBinding(Key::Down),
+            left: KeyBinding(Key::Left),
+            right: KeyBinding(Key::Right),
+            autopilot: KeyBinding(Key::A),
+            restart: KeyBinding(Key::R),
+            high_scores: KeyBinding(Key::H),
+            pause: KeyBinding(Key::P),
+            player2_up: KeyBinding(Key::W),
+            player2_down: KeyBinding(Key::S),
+            player2_left: KeyBinding(Key::A),
+            player2_right: KeyBinding(Key::D),
+        }
+    }
+}
+
+impl Config {
+    /// Reads and validates the config at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&contents)
+    }
+
+    /// Parses and validates the contents of a config file.
+    fn parse(contents: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(contents).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Applies the board options given on the command line, which win over
+    /// the file.
+    pub fn override_board(&mut self, board: &BoardArgs) {
+        if let Some(mode) = board.mode {
+            self.game.mode = mode;
+        }
+        if let Some(width) = board.width {
+            self.game.width = width;
+        }
+        if let Some(height) = board.height {
+            self.game.height = height;
+        }
+        if let Some(difficulty) = board.difficulty {
+            self.game.difficulty = Some(difficulty);
+            self.speed = None;
+        }
+    }
+
+    /// Applies all the `play` options given on the command line.
+    pub fn override_play(&mut self, play: &PlayArgs) {
+        self.override_board(&play.board);
+        if let Some(cell_size) = play.cell_size {
+            self.display.cell_size = cell_size;
+        }
+        if let Some(format) = play.stats_format {
+            self.stats.format = format;
+        }
+        if let Some(dir) = &play.stats_dir {
+            self.stats.dir = Some(dir.clone());
+        }
+    }
+
+    /// The stats directory, defaulting to the one in the data directory.
+    pub fn stats_dir(&self) -> PathBuf {
+        self.stats
+            .dir
+            .clone()
+            .unwrap_or_else(paths::default_stats_dir)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.game.difficulty, self.speed) {
+            (Some(Difficulty::Custom), None) => {
+                return Err("invalid [game]: difficulty `custom` needs a [speed] section".into())
+            }
+            (Some(difficulty), Some(_)) if difficulty != Difficulty::Custom => {
+                return Err(format!(
+                    "invalid [speed]: only used with difficulty `custom`, not `{}`",
+                    difficulty
+                ))
+            }
+            (_, Some(speed)) => speed
+                .validate()
+                .map_err(|e| format!("invalid [speed]: {}", e))?,
+            _ => {}
+        }
+        self.game_settings()
+            .validate()
+            .map_err(|e| format!("invalid [game]: {}", e))?;
+        if !(MIN_CELL_SIZE..=MAX_CELL_SIZE).contains(&self.display.cell_size) {
+            return Err(format!(
+                "invalid [display]: cell_size must be between {} and {}, got {}",
+                MIN_CELL_SIZE, MAX_CELL_SIZE, self.display.cell_size
+            ));
+        }
+        self.keys
+            .validate()
+            .map_err(|e| format!("invalid [keys]: {}", e))
+    }
+
+    pub fn game_settings(&self) -> GameSettings {
+        let mut settings = GameSettings {
+            boundary: self.game.mode,
+            width: self.game.width,
+            height: self.game.height,
+            ..GameSettings::default()
+        };
+        match self.speed {
+            Some(speed) => settings.set_custom_speed(speed),
+            None => settings.set_difficulty(self.game.difficulty.unwrap_or_default()),
+        }
+        settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_is_the_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.game_settings(), GameSettings::default());
+        assert_eq!(config.display.cell_size, DEFAULT_CELL_SIZE);
+    }
+
+    #[test]
+    fn unknown_fields_are_refused() {
+        let error = Config::parse("[game]\nwidht = 30\n").err().unwrap();
+        assert!(error.contains("unknown field `widht`"), "{}", error);
+        assert!(Config::parse("[sound]\nvolume = 3\n").is_err());
+    }
+
+    #[test]
+    fn sizes_out_of_range_are_refused() {
+        let error = Config::parse("[game]\nwidth = 2\n").err().unwrap();
+        assert!(
+            error.starts_with("invalid [game]: board width"),
+            "{}",
+            error
+        );
+        let error = Config::parse("[display]\ncell_size = 0\n").err().unwrap();
+        assert!(error.starts_with("invalid [display]"), "{}", error);
+        assert!(Config::parse("[game]\nwidth = 4\nheight = 1024\n").is_ok());
+    }
+
+    #[test]
+    fn custom_difficulty_needs_a_speed_section() {
+        let error = Config::parse("[game]\ndifficulty = \"custom\"\n")
+            .err()
+            .unwrap();
+        assert!(error.contains("needs a [speed] section"), "{}", error);
+
+        let speed = "[speed]\ninitial_ms = 90\nstep_ms = 1\nminimum_ms = 40\n";
+        assert!(Config::parse(&format!("[game]\ndifficulty = \"hard\"\n{}", speed)).is_err());
+        // A speed section on its own makes the difficulty custom
+        let config = Config::parse(speed).unwrap();
+        assert_eq!(config.game_settings().difficulty, Difficulty::Custom);
+        assert_eq!(config.game_settings().speed.initial_ms, 90);
+    }
+
+    #[test]
+    fn keys_bound_twice_are_refused() {
+        let error = Config::parse("[keys]\nup = \"Down\"\n").err().unwrap();
+        assert!(error.starts_with("invalid [keys]"), "{}", error);
+        // The second player may share the autopilot key
+        assert!(Config::parse("[keys]\nplayer2_left = \"A\"\n").is_ok());
+    }
+
+    #[test]
+    fn command_line_wins_over_the_file() {
+        let mut config = Config::parse(
+            "[game]\nmode = \"walls\"\nwidth = 30\n\
+             [speed]\ninitial_ms = 90\nstep_ms = 1\nminimum_ms = 40\n\
+             [stats]\nformat = \"csv\"\n",
+        )
+        .unwrap();
+        config.override_play(&PlayArgs {
+            board: BoardArgs {
+                width: Some(16),
+                difficulty: Some(Difficulty::Easy),
+                ..BoardArgs::default()
+            },
+            stats_format: Some(StatsFormat::Json),
+            ..PlayArgs::default()
+        });
+        config.validate().unwrap();
+
+        let settings = config.game_settings();
+        assert_eq!(settings.boundary, BoundaryMode::Walls);
+        assert_eq!((settings.width, settings.height), (16, DEFAULT_GRID_SIZE));
+        // A difficulty on the command line replaces the file's custom curve
+        assert_eq!(settings.difficulty, Difficulty::Easy);
+        assert_eq!(config.stats.format, StatsFormat::Json);
+    }
+
+    #[test]
+    fn stats_go_to_the_data_directory_unless_told_otherwise() {
+        let default = Config::parse("").unwrap().stats_dir();
+        assert_eq!(default, paths::default_stats_dir());
+        assert!(default.ends_with(Path::new(paths::APP_DIR_NAME).join("stats")));
+
+        let mut config = Config::parse("[stats]\ndir = \"/srv/snake\"\n").unwrap();
+        assert_eq!(config.stats_dir(), PathBuf::from("/srv/snake"));
+        config.override_play(&PlayArgs {
+            stats_dir: Some(PathBuf::from("elsewhere")),
+            ..PlayArgs::default()
+        });
+        assert_eq!(config.stats_dir(), PathBuf::from("elsewhere"));
+    }
+}
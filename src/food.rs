@@ -0,0 +1,56 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::game::Position;
+use crate::grid::Grid;
+
+/// The food on the board and the generator that places it. Single-player
+/// games and versus rounds both go through this, so a seed puts food in the
+/// same cells whatever kind of round it drives.
+#[derive(Clone, Debug)]
+pub(crate) struct Food {
+    position: Position,
+    rng: ChaCha8Rng,
+}
+
+impl Food {
+    /// Seeds the generator with `seed` and puts the first food on a free
+    /// cell of `grid`.
+    pub fn new(seed: u64, grid: &Grid) -> Self {
+        let mut food = Self {
+            position: Position { x: 0, y: 0 },
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        food.respawn(grid);
+        food
+    }
+
+    /// Food at `position` with the generator for `seed` moved on to
+    /// `word_pos`, as read back from [`Food::word_pos`].
+    pub fn restore(seed: u64, word_pos: u128, position: Position) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_word_pos(word_pos);
+        Self { position, rng }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// How far the generator has got, in 32-bit words.
+    pub fn word_pos(&self) -> u128 {
+        self.rng.get_word_pos()
+    }
+
+    /// Moves the food to a random free cell of `grid`. Returns `false`, and
+    /// leaves it where it was, if the board is full.
+    pub fn respawn(&mut self, grid: &Grid) -> bool {
+        match grid.random_free(&mut self.rng) {
+            Some(position) => {
+                self.position = position;
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,673 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::food::Food;
+use crate::grid::Grid;
+use crate::replay::{Replay, ReplayInput, REPLAY_EXTENSION};
+use crate::stats::{GameStats, StatsFormat};
+
+/// Board width and height used unless the settings say otherwise.
+pub const DEFAULT_GRID_SIZE: u32 = 20;
+
+/// Smallest and largest board side [`GameSettings::validate`] accepts.
+pub const MIN_GRID_SIZE: u32 = 4;
+pub const MAX_GRID_SIZE: u32 = 1024;
+
+/// Most ticks a single [`Game::update`] call will run to catch up after a
+/// long frame; anything beyond that is dropped instead of fast-forwarding.
+pub const MAX_CATCH_UP_TICKS: u32 = 5;
+
+/// Time left over between updates on a fixed timestep. Every kind of round
+/// ticks through this, so they all catch up after a long frame the same way.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Timestep {
+    accumulator: f64,
+    /// Ticks taken since the last [`Timestep::advance`]
+    ticks: u32,
+}
+
+impl Timestep {
+    /// Adds `dt` seconds to run ticks for, starting a new update.
+    pub fn advance(&mut self, dt: f64) {
+        self.accumulator += dt;
+        self.ticks = 0;
+    }
+
+    /// Takes a tick of `interval` seconds if one is due. After `max_ticks`
+    /// in one update the rest of the backlog is dropped instead of
+    /// fast-forwarding (e.g. the window was dragged).
+    pub fn take_tick(&mut self, interval: f64, max_ticks: u32) -> bool {
+        if self.accumulator < interval {
+            return false;
+        }
+        if self.ticks == max_ticks {
+            self.accumulator = 0.0;
+            return false;
+        }
+        self.accumulator -= interval;
+        self.ticks += 1;
+        true
+    }
+}
+
+/// Turns that can be buffered ahead of the snake. One is consumed per tick.
+pub const MAX_QUEUED_INPUTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+/// What happens when the snake runs off the edge of the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoundaryMode {
+    /// Leave one edge, come back in on the opposite one.
+    #[default]
+    Wrap,
+    /// Touching an edge ends the game.
+    Walls,
+}
+
+impl BoundaryMode {
+    pub const ALL: [BoundaryMode; 2] = [BoundaryMode::Wrap, BoundaryMode::Walls];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BoundaryMode::Wrap => "wrap",
+            BoundaryMode::Walls => "walls",
+        }
+    }
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(BoundaryMode::Wrap),
+            "walls" => Ok(BoundaryMode::Walls),
+            _ => Err(format!(
+                "unknown boundary mode `{}` (expected wrap or walls)",
+                s
+            )),
+        }
+    }
+}
+
+/// How the time between ticks shrinks as the score goes up, in milliseconds.
+/// The default is the [`Difficulty::Normal`] curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeedCurve {
+    /// Time between ticks at the start of a game
+    pub initial_ms: u32,
+    /// Taken off the time between ticks for every point scored
+    pub step_ms: u32,
+    /// The time between ticks never drops below this
+    pub minimum_ms: u32,
+}
+
+impl SpeedCurve {
+    /// Seconds between ticks once `score` points have been scored.
+    pub fn interval(&self, score: u32) -> f64 {
+        let ms = self
+            .initial_ms
+            .saturating_sub(score.saturating_mul(self.step_ms))
+            .max(self.minimum_ms);
+        ms as f64 / 1000.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.minimum_ms == 0 {
+            return Err("minimum_ms must be greater than 0".to_string());
+        }
+        if self.minimum_ms > self.initial_ms {
+            return Err(format!(
+                "minimum_ms ({}) must not be greater than initial_ms ({})",
+                self.minimum_ms, self.initial_ms
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SpeedCurve {
+    fn default() -> Self {
+        Self {
+            initial_ms: 100,
+            step_ms: 2,
+            minimum_ms: 50,
+        }
+    }
+}
+
+/// Named speed curves. `Custom` marks a curve that came from a config file
+/// rather than a preset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+    Custom,
+}
+
+impl Difficulty {
+    /// Every difficulty except `Custom`, easiest first.
+    pub const PRESETS: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Insane,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Insane => "insane",
+            Difficulty::Custom => "custom",
+        }
+    }
+
+    /// The preset's speed curve, or `None` for `Custom`.
+    pub fn speed_curve(&self) -> Option<SpeedCurve> {
+        let (initial_ms, step_ms, minimum_ms) = match self {
+            Difficulty::Easy => (150, 1, 80),
+            Difficulty::Normal => (100, 2, 50),
+            Difficulty::Hard => (75, 2, 35),
+            Difficulty::Insane => (50, 1, 25),
+            Difficulty::Custom => return None,
+        };
+        Some(SpeedCurve {
+            initial_ms,
+            step_ms,
+            minimum_ms,
+        })
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Only parses presets; a custom curve has to be given as a [`SpeedCurve`].
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::PRESETS
+            .into_iter()
+            .find(|difficulty| difficulty.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown difficulty `{}` (expected easy, normal, hard or insane)",
+                    s
+                )
+            })
+    }
+}
+
+/// Rules a game is played with, fixed for its whole lifetime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub boundary: BoundaryMode,
+    /// Board size in cells
+    pub width: u32,
+    pub height: u32,
+    pub difficulty: Difficulty,
+    /// Always the difficulty's own curve unless it is `Custom`
+    pub speed: SpeedCurve,
+}
+
+impl GameSettings {
+    /// Switches to a preset difficulty and its speed curve. Does nothing for
+    /// `Custom`, which needs [`GameSettings::set_custom_speed`].
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        if let Some(speed) = difficulty.speed_curve() {
+            self.difficulty = difficulty;
+            self.speed = speed;
+        }
+    }
+
+    /// Switches to a custom speed curve.
+    pub fn set_custom_speed(&mut self, speed: SpeedCurve) {
+        self.difficulty = Difficulty::Custom;
+        self.speed = speed;
+    }
+
+    /// Checks the board dimensions are within
+    /// [`MIN_GRID_SIZE`]..=[`MAX_GRID_SIZE`] and the speed curve makes sense
+    /// and belongs to the difficulty.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [("width", self.width), ("height", self.height)] {
+            if !(MIN_GRID_SIZE..=MAX_GRID_SIZE).contains(&value) {
+                return Err(format!(
+                    "board {} must be between {} and {} cells, got {}",
+                    name, MIN_GRID_SIZE, MAX_GRID_SIZE, value
+                ));
+            }
+        }
+        if let Some(speed) = self.difficulty.speed_curve() {
+            if speed != self.speed {
+                return Err(format!(
+                    "speed curve does not match the {} difficulty",
+                    self.difficulty
+                ));
+            }
+        }
+        self.speed.validate()
+    }
+
+    /// Number of cells on the board.
+    pub fn cell_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Cell one step from `from` in `direction` under these boundary rules,
+    /// or `None` if that step leaves a walled board.
+    pub fn neighbor(&self, from: Position, direction: Direction) -> Option<Position> {
+        let (width, height) = (self.width, self.height);
+        let (x, y) = match self.boundary {
+            BoundaryMode::Wrap => match direction {
+                Direction::Up => (from.x, (from.y + height - 1) % height),
+                Direction::Down => (from.x, (from.y + 1) % height),
+                Direction::Left => ((from.x + width - 1) % width, from.y),
+                Direction::Right => ((from.x + 1) % width, from.y),
+            },
+            BoundaryMode::Walls => match direction {
+                Direction::Up => (from.x, from.y.checked_sub(1)?),
+                Direction::Down => (from.x, from.y + 1),
+                Direction::Left => (from.x.checked_sub(1)?, from.y),
+                Direction::Right => (from.x + 1, from.y),
+            },
+        };
+
+        if x >= width || y >= height {
+            return None;
+        }
+        Some(Position { x, y })
+    }
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            boundary: BoundaryMode::default(),
+            width: DEFAULT_GRID_SIZE,
+            height: DEFAULT_GRID_SIZE,
+            difficulty: Difficulty::default(),
+            speed: SpeedCurve::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: u32,
+    pub y: u32,
+}
+
+pub struct Game {
+    snake: VecDeque<Position>,
+    grid: Grid,
+    food: Food,
+    direction: Direction,
+    input_queue: VecDeque<Direction>,
+    is_game_over: bool,
+    is_won: bool,
+    is_paused: bool,
+    score: u32,
+    speed: f64,
+    tick: u64,
+    timestep: Timestep,
+    stats: GameStats,
+    settings: GameSettings,
+    seed: u64,
+    inputs: Vec<ReplayInput>,
+}
+
+impl Game {
+    /// Starts a game with a fresh random seed.
+    pub fn new() -> Game {
+        Game::with_seed(rand::random())
+    }
+
+    /// Starts a game whose every random decision is derived from `seed`, so
+    /// the same seed and the same inputs always play out the same way.
+    pub fn with_seed(seed: u64) -> Game {
+        Game::with_settings(GameSettings::default(), seed)
+    }
+
+    /// # Panics
+    ///
+    /// If `settings` fail [`GameSettings::validate`].
+    pub fn with_settings(settings: GameSettings, seed: u64) -> Game {
+        if let Err(e) = settings.validate() {
+            panic!("invalid game settings: {}", e);
+        }
+
+        let start = Position {
+            x: settings.width / 2,
+            y: settings.height / 2,
+        };
+        let mut grid = Grid::new(settings.width, settings.height);
+        grid.occupy(start);
+
+        Game {
+            snake: VecDeque::from([start]),
+            food: Food::new(seed, &grid),
+            grid,
+            direction: Direction::Right,
+            input_queue: VecDeque::with_capacity(MAX_QUEUED_INPUTS),
+            is_game_over: false,
+            is_won: false,
+            is_paused: false,
+            score: 0,
+            speed: settings.speed.interval(0), // Time between updates in seconds
+            tick: 0,
+            timestep: Timestep::default(),
+            stats: GameStats::new(seed, settings),
+            settings,
+            seed,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Body segments, head first.
+    pub fn snake(&self) -> &VecDeque<Position> {
+        &self.snake
+    }
+
+    pub fn head(&self) -> Position {
+        self.snake[0]
+    }
+
+    /// Whether any part of the snake is on `position`.
+    pub fn is_occupied(&self, position: Position) -> bool {
+        self.grid.is_occupied(position)
+    }
+
+    pub fn food(&self) -> Position {
+        self.food.position()
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.is_game_over
+    }
+
+    /// Whether the snake has filled the whole board. There is no food left
+    /// once this is set.
+    pub fn is_won(&self) -> bool {
+        self.is_won
+    }
+
+    /// Whether the game has ended, either lost or won.
+    pub fn is_finished(&self) -> bool {
+        self.is_game_over || self.is_won
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Pauses or resumes the game. While paused, [`Game::update`] does not
+    /// tick or count time played and turns are ignored.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.is_paused = paused;
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Seconds between two ticks at the current score.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Number of ticks simulated so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn stats(&self) -> &GameStats {
+        &self.stats
+    }
+
+    /// Everything needed to play this game back: its seed, settings and
+    /// every accepted turn stamped with the tick it was queued on.
+    pub fn replay(&self) -> Replay {
+        Replay::new(self.settings, self.seed, self.inputs.clone())
+    }
+
+    /// Saves [`Game::replay`] into `dir`, named after the game's start time,
+    /// and returns the file written to. A game that started in the same
+    /// second as one already saved gets a numbered name (`_2`, `_3`, ...)
+    /// rather than overwriting it.
+    pub fn save_replay(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        let dt: DateTime<Local> = self.stats.start_time.into();
+        let stem = format!("{}_snake_game", dt.format("%Y%m%d_%H%M%S"));
+        let replay = self.replay();
+        let mut number = 1;
+        loop {
+            let name = match number {
+                1 => format!("{}.{}", stem, REPLAY_EXTENSION),
+                _ => format!("{}_{}.{}", stem, number, REPLAY_EXTENSION),
+            };
+            let path = dir.join(name);
+            match replay.save_new(&path) {
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => number += 1,
+                result => return result.map(|()| path),
+            }
+        }
+    }
+
+    /// Writes this game's stats in `format`, see [`GameStats::save`].
+    pub fn save_stats(&self, dir: &Path, format: StatsFormat) -> std::io::Result<PathBuf> {
+        self.stats.save(dir, format, self.score, self.snake.len())
+    }
+
+    /// Advances the clock by `dt` seconds and runs every tick that became
+    /// due, carrying the leftover time over to the next call. Returns `true`
+    /// when the board changed.
+    pub fn update(&mut self, dt: f64) -> bool {
+        self.update_with(dt, |_| {})
+    }
+
+    /// Like [`Game::update`], but calls `before_tick` ahead of every tick so
+    /// a controller such as a bot can queue input against the latest board.
+    pub fn update_with<F: FnMut(&mut Game)>(&mut self, dt: f64, mut before_tick: F) -> bool {
+        if self.is_finished() || self.is_paused {
+            return false;
+        }
+
+        // Update stats time played
+        self.stats.add_play_time(dt);
+
+        self.timestep.advance(dt);
+        let mut ticked = false;
+        while !self.is_finished() && self.timestep.take_tick(self.speed, MAX_CATCH_UP_TICKS) {
+            before_tick(self);
+            ticked |= self.step();
+        }
+
+        ticked
+    }
+
+    /// Moves the snake one cell regardless of timing. Returns `false` if the
+    /// game had already finished.
+    pub fn step(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.tick += 1;
+
+        if let Some(direction) = self.input_queue.pop_front() {
+            self.direction = direction;
+        }
+
+        let new_head = match self.next_head() {
+            Some(position) => position,
+            None => {
+                // Ran into a wall
+                self.is_game_over = true;
+                return true;
+            }
+        };
+
+        // Check collision with self
+        if self.grid.is_occupied(new_head) {
+            self.is_game_over = true;
+            return true;
+        }
+
+        self.snake.push_front(new_head);
+        self.grid.occupy(new_head);
+
+        if new_head == self.food.position() {
+            // Ate food, grow snake and spawn new food
+            self.score += 1;
+            self.stats.food_eaten += 1;
+            self.speed = self.settings.speed.interval(self.score); // Speed up as score increases
+            if !self.food.respawn(&self.grid) {
+                // Nowhere left to put food: the board is cleared
+                self.is_won = true;
+                self.stats.board_cleared = true;
+            }
+        } else {
+            // Remove tail if no food was eaten
+            if let Some(tail) = self.snake.pop_back() {
+                self.grid.release(tail);
+            }
+        }
+
+        true
+    }
+
+    /// Cell the head moves into on the next tick, or `None` if that means
+    /// leaving the board in walls mode.
+    fn next_head(&self) -> Option<Position> {
+        self.neighbor(self.head(), self.direction)
+    }
+
+    /// [`GameSettings::neighbor`] for this game's settings.
+    pub fn neighbor(&self, from: Position, direction: Direction) -> Option<Position> {
+        self.settings.neighbor(from, direction)
+    }
+
+    /// Queues a turn for an upcoming tick. The turn is checked against the
+    /// direction the snake will be moving in once everything already queued
+    /// has been applied, so quick double turns can never reverse it. Ignored
+    /// while the game is paused.
+    pub fn change_direction(&mut self, new_direction: Direction) {
+        if self.is_paused || !queue_turn(&mut self.input_queue, self.direction, new_direction) {
+            return;
+        }
+
+        self.stats.count_turn(new_direction);
+        self.inputs.push(ReplayInput {
+            tick: self.tick,
+            direction: new_direction,
+        });
+    }
+}
+
+/// Queues `new_direction` behind the turns already in `queue` for a snake
+/// moving in `direction`. Turns that would not change course or would
+/// reverse the snake, and turns past [`MAX_QUEUED_INPUTS`], are dropped.
+/// Returns whether the turn was queued.
+pub(crate) fn queue_turn(
+    queue: &mut VecDeque<Direction>,
+    direction: Direction,
+    new_direction: Direction,
+) -> bool {
+    let effective = queue.back().copied().unwrap_or(direction);
+    if new_direction == effective
+        || new_direction == effective.opposite()
+        || queue.len() == MAX_QUEUED_INPUTS
+    {
+        return false;
+    }
+
+    queue.push_back(new_direction);
+    true
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl Game {
+    /// A game part way through with `snake` laid out head first, for trying
+    /// bots on hand-built boards.
+    pub(crate) fn with_snake(
+        settings: GameSettings,
+        snake: &[Position],
+        direction: Direction,
+        food: Position,
+    ) -> Game {
+        let mut game = Game::with_settings(settings, 0);
+        game.grid = Grid::new(settings.width, settings.height);
+        for segment in snake {
+            game.grid.occupy(*segment);
+        }
+        game.snake = snake.iter().copied().collect();
+        game.food = Food::restore(0, 0, food);
+        game.direction = direction;
+        game
+    }
+}
@@ -0,0 +1,204 @@
+use rand::Rng;
+
+use crate::game::Position;
+
+/// Which cells of the board are taken, plus a list of the free ones, so that
+/// marking a cell, testing it and picking a random free cell are all
+/// constant time no matter how large the board or the snake gets.
+#[derive(Clone, Debug)]
+pub(crate) struct Grid {
+    width: u32,
+    occupied: Vec<bool>,
+    /// Every free cell, in no particular order
+    free: Vec<Position>,
+    /// Where each free cell sits in `free`, indexed like the board
+    free_index: Vec<usize>,
+}
+
+impl Grid {
+    pub fn new(width: u32, height: u32) -> Self {
+        let free: Vec<Position> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Position { x, y }))
+            .collect();
+        let free_index = (0..free.len()).collect();
+
+        Self {
+            width,
+            occupied: vec![false; free.len()],
+            free,
+            free_index,
+        }
+    }
+
+    /// A grid with exactly `free` left free, kept in that order so food is
+    /// picked from it just like on the grid it was copied from. `None` if a
+    /// cell is off the board or listed twice.
+    pub fn with_free(width: u32, height: u32, free: &[Position]) -> Option<Self> {
+        let mut grid = Self {
+            width,
+            occupied: vec![true; (width * height) as usize],
+            free: Vec::with_capacity(free.len()),
+            free_index: vec![0; (width * height) as usize],
+        };
+        for &position in free {
+            if position.x >= width || position.y >= height || !grid.is_occupied(position) {
+                return None;
+            }
+            grid.release(position);
+        }
+        Some(grid)
+    }
+
+    /// Every free cell, in the order [`Grid::random_free`] picks from.
+    pub fn free(&self) -> &[Position] {
+        &self.free
+    }
+
+    fn index(&self, position: Position) -> usize {
+        (position.y * self.width + position.x) as usize
+    }
+
+    pub fn is_occupied(&self, position: Position) -> bool {
+        self.occupied[self.index(position)]
+    }
+
+    pub fn occupy(&mut self, position: Position) {
+        let i = self.index(position);
+        if self.occupied[i] {
+            return;
+        }
+        self.occupied[i] = true;
+
+        // Swap the cell out of the free list and patch up the one moved in
+        let slot = self.free_index[i];
+        self.free.swap_remove(slot);
+        if let Some(moved) = self.free.get(slot) {
+            let moved = self.index(*moved);
+            self.free_index[moved] = slot;
+        }
+    }
+
+    pub fn release(&mut self, position: Position) {
+        let i = self.index(position);
+        if !self.occupied[i] {
+            return;
+        }
+        self.occupied[i] = false;
+        self.free_index[i] = self.free.len();
+        self.free.push(position);
+    }
+
+    /// A uniformly chosen free cell, or `None` if the board is full.
+    pub fn random_free<R: Rng>(&self, rng: &mut R) -> Option<Position> {
+        if self.free.is_empty() {
+            return None;
+        }
+        Some(self.free[rng.random_range(0..self.free.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn cells(width: u32, height: u32) -> impl Iterator<Item = Position> {
+        (0..height).flat_map(move |y| (0..width).map(move |x| Position { x, y }))
+    }
+
+    /// Every cell is either occupied or in the free list exactly once, at the
+    /// slot `free_index` says.
+    fn assert_consistent(grid: &Grid, width: u32, height: u32) {
+        let mut free = 0;
+        for position in cells(width, height) {
+            if grid.is_occupied(position) {
+                assert!(!grid.free.contains(&position), "{:?} taken", position);
+            } else {
+                free += 1;
+                let slot = grid.free_index[grid.index(position)];
+                assert_eq!(grid.free[slot], position);
+            }
+        }
+        assert_eq!(grid.free.len(), free);
+    }
+
+    #[test]
+    fn occupy_and_release_keep_the_free_list_in_step() {
+        let (width, height) = (7, 5);
+        let mut grid = Grid::new(width, height);
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        assert_consistent(&grid, width, height);
+
+        for _ in 0..2000 {
+            let position = Position {
+                x: rng.random_range(0..width),
+                y: rng.random_range(0..height),
+            };
+            if rng.random_bool(0.5) {
+                grid.occupy(position);
+                assert!(grid.is_occupied(position));
+            } else {
+                grid.release(position);
+                assert!(!grid.is_occupied(position));
+            }
+            assert_consistent(&grid, width, height);
+        }
+
+        // Doing it twice changes nothing
+        let position = Position { x: 2, y: 3 };
+        grid.occupy(position);
+        let free = grid.free.clone();
+        grid.occupy(position);
+        assert_eq!(grid.free, free);
+        grid.release(position);
+        let free = grid.free.clone();
+        grid.release(position);
+        assert_eq!(grid.free, free);
+        assert_consistent(&grid, width, height);
+    }
+
+    #[test]
+    fn random_free_only_picks_free_cells() {
+        let (width, height) = (6, 6);
+        let mut grid = Grid::new(width, height);
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+
+        // Fill the board one random pick at a time
+        for taken in 0..width * height {
+            assert_eq!(grid.free().len(), (width * height - taken) as usize);
+            let position = grid.random_free(&mut rng).unwrap();
+            assert!(!grid.is_occupied(position));
+            grid.occupy(position);
+        }
+        assert_eq!(grid.random_free(&mut rng), None);
+
+        grid.release(Position { x: 4, y: 1 });
+        for _ in 0..20 {
+            assert_eq!(grid.random_free(&mut rng), Some(Position { x: 4, y: 1 }));
+        }
+    }
+
+    #[test]
+    fn with_free_keeps_the_order_given() {
+        let free = [
+            Position { x: 2, y: 1 },
+            Position { x: 0, y: 0 },
+            Position { x: 3, y: 2 },
+        ];
+        let grid = Grid::with_free(4, 3, &free).unwrap();
+        assert_eq!(grid.free(), free);
+        assert_consistent(&grid, 4, 3);
+    }
+
+    #[test]
+    fn with_free_rejects_duplicate_and_off_board_cells() {
+        let twice = [Position { x: 1, y: 1 }, Position { x: 1, y: 1 }];
+        assert!(Grid::with_free(4, 3, &twice).is_none());
+
+        let off_board = [Position { x: 0, y: 0 }, Position { x: 4, y: 0 }];
+        assert!(Grid::with_free(4, 3, &off_board).is_none());
+        let off_board = [Position { x: 0, y: 3 }];
+        assert!(Grid::with_free(4, 3, &off_board).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::game::{Difficulty, Game, GameSettings};
+use crate::paths;
+
+/// Entries kept per category.
+pub const MAX_HIGH_SCORES: usize = 10;
+
+/// Longest name accepted for an entry.
+pub const MAX_NAME_LEN: usize = 12;
+
+/// Scores are only compared between games played under the same rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoreCategory {
+    pub settings: GameSettings,
+}
+
+impl ScoreCategory {
+    pub fn new(settings: GameSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Key the category is stored under, e.g. `wrap 20x20 normal`.
+    pub fn key(&self) -> String {
+        format!(
+            "{} {}x{} {}",
+            self.settings.boundary,
+            self.settings.width,
+            self.settings.height,
+            self.settings.difficulty
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub length: usize,
+    pub seed: u64,
+    pub timestamp: u64,
+}
+
+impl HighScore {
+    pub fn from_game(name: &str, game: &Game) -> Self {
+        Self {
+            name: name.chars().take(MAX_NAME_LEN).collect(),
+            score: game.score(),
+            length: game.snake().len(),
+            seed: game.seed(),
+            timestamp: game.stats().timestamp,
+        }
+    }
+}
+
+/// Top scores per [`ScoreCategory`], persisted as JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    categories: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScoreTable {
+    /// `highscores.json` in the per-user data directory.
+    pub fn default_path() -> PathBuf {
+        paths::data_dir().join("highscores.json")
+    }
+
+    /// Reads the table at `path`; a missing file is an empty table.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut table: Self = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        // Tables from before difficulties existed only hold normal games
+        let legacy: Vec<String> = table
+            .categories
+            .keys()
+            .filter(|key| key.split(' ').count() == 2)
+            .cloned()
+            .collect();
+        for key in legacy {
+            if let Some(entries) = table.categories.remove(&key) {
+                let normal = format!("{} {}", key, Difficulty::Normal);
+                table.categories.entry(normal).or_insert(entries);
+            }
+        }
+
+        Ok(table)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Entries for `category`, best first.
+    pub fn entries(&self, category: &ScoreCategory) -> &[HighScore] {
+        self.categories
+            .get(&category.key())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn best(&self, category: &ScoreCategory) -> u32 {
+        self.entries(category)
+            .first()
+            .map_or(0, |entry| entry.score)
+    }
+
+    /// Whether `score` would make it onto the table.
+    pub fn qualifies(&self, category: &ScoreCategory, score: u32) -> bool {
+        let entries = self.entries(category);
+        score > 0
+            && (entries.len() < MAX_HIGH_SCORES || entries.last().is_some_and(|e| score > e.score))
+    }
+
+    /// Adds `entry` below any equal scores and returns its rank, or `None` if
+    /// it did not make the cut.
+    pub fn insert(&mut self, category: &ScoreCategory, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(category, entry.score) {
+            return None;
+        }
+
+        let entries = self.categories.entry(category.key()).or_default();
+        let rank = entries
+            .iter()
+            .position(|e| entry.score > e.score)
+            .unwrap_or(entries.len());
+        entries.insert(rank, entry);
+        entries.truncate(MAX_HIGH_SCORES);
+
+        Some(rank)
+    }
+}
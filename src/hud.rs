@@ -0,0 +1,238 @@
+use piston_window::*;
+use rust_snake_game::{Game, HighScore, Versus};
+use std::sync::Once;
+
+/// Height of the strip above the board that holds the HUD.
+pub const HUD_HEIGHT: u32 = 40;
+
+const FONT_FILE: &str = "DejaVuSansMono.ttf";
+const FONT_SIZE: types::FontSize = 13;
+pub const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const BACKGROUND_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
+pub const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.85];
+const LINE_HEIGHT: f64 = 20.0;
+
+/// Loads the bundled HUD font from the `assets/` folder next to the working
+/// directory. The game stays playable without it, just without text.
+pub fn load_font(window: &mut PistonWindow) -> Option<Glyphs> {
+    let assets = match find_folder::Search::ParentsThenKids(3, 3).for_folder("assets") {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error locating assets folder: {}", e);
+            return None;
+        }
+    };
+
+    match window.load_font(assets.join(FONT_FILE)) {
+        Ok(glyphs) => Some(glyphs),
+        Err(e) => {
+            eprintln!("Error loading font {}: {}", FONT_FILE, e);
+            None
+        }
+    }
+}
+
+/// Draws the score, length, elapsed time, speed and high score in the strip
+/// at the top of the window. The high score is left out when there is none.
+pub fn draw(
+    game: &Game,
+    high_score: Option<u32>,
+    glyphs: &mut Glyphs,
+    width: f64,
+    c: &Context,
+    g: &mut G2d,
+) {
+    draw_strip(&status_lines(game, high_score), glyphs, width, c, g);
+}
+
+/// Draws both players' scores and lengths, the elapsed time and the speed of
+/// a versus round in the strip at the top of the window.
+pub fn draw_versus(versus: &Versus, glyphs: &mut Glyphs, width: f64, c: &Context, g: &mut G2d) {
+    draw_strip(&versus_status_lines(versus), glyphs, width, c, g);
+}
+
+fn draw_strip(lines: &[String; 2], glyphs: &mut Glyphs, width: f64, c: &Context, g: &mut G2d) {
+    rectangle(
+        BACKGROUND_COLOR,
+        [0.0, 0.0, width, HUD_HEIGHT as f64],
+        c.transform,
+        g,
+    );
+    draw_text(&lines[0], [8.0, 16.0], glyphs, c, g);
+    draw_text(&lines[1], [8.0, 33.0], glyphs, c, g);
+}
+
+/// The two lines of the HUD strip for a game: score, length and high score,
+/// then elapsed time, speed and mode.
+pub fn status_lines(game: &Game, high_score: Option<u32>) -> [String; 2] {
+    let played = game.stats().time_played.as_secs();
+    let mut top = format!("Score {:<4} Length {:<4}", game.score(), game.snake().len());
+    if let Some(high_score) = high_score {
+        top.push_str(&format!(" Best {}", high_score.max(game.score())));
+    }
+    let bottom = format!(
+        "Time {}:{:02}  Speed {:.1}/s  Mode {} {}",
+        played / 60,
+        played % 60,
+        1.0 / game.speed(),
+        game.settings().boundary,
+        game.settings().difficulty
+    );
+    [top, bottom]
+}
+
+/// The two lines of the HUD strip for a versus round.
+pub fn versus_status_lines(versus: &Versus) -> [String; 2] {
+    let [first, second] = versus.players();
+    let played = first.stats().time_played.as_secs();
+    let top = format!(
+        "P1 {:<4} Length {:<4} P2 {:<4} Length {}",
+        first.score(),
+        first.snake().len(),
+        second.score(),
+        second.snake().len()
+    );
+    let bottom = format!(
+        "Time {}:{:02}  Speed {:.1}/s  Mode {} {}",
+        played / 60,
+        played % 60,
+        1.0 / versus.speed(),
+        versus.settings().boundary,
+        versus.settings().difficulty
+    );
+    [top, bottom]
+}
+
+/// The high-score table under `title`, as a menu with nothing to choose.
+pub fn high_scores_menu(entries: &[HighScore], title: String) -> Menu {
+    let mut lines: Vec<String> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| format!("{:>2}. {:<12} {:>5}", i + 1, entry.name, entry.score))
+        .collect();
+    if lines.is_empty() {
+        lines.push("No scores yet".to_string());
+    }
+    Menu {
+        title,
+        lines,
+        items: Vec::new(),
+        selected: 0,
+        hint: "Enter or Esc to go back".to_string(),
+    }
+}
+
+/// A screen of text over the dimmed board: a title, some lines of
+/// information and a list of choices with one selected.
+pub struct Menu {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub items: Vec<String>,
+    pub selected: usize,
+    /// Shown at the bottom, usually which keys do what
+    pub hint: String,
+}
+
+impl Menu {
+    /// The items with the selected one marked, padded to the same width so
+    /// they line up when centred.
+    pub fn item_lines(&self) -> Vec<String> {
+        let item_width = self.items.iter().map(|item| item.chars().count()).max();
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let marker = if i == self.selected { '>' } else { ' ' };
+                format!("{} {:<2$}", marker, item, item_width.unwrap_or(0))
+            })
+            .collect()
+    }
+}
+
+pub fn draw_menu(
+    menu: &Menu,
+    width: f64,
+    height: f64,
+    glyphs: &mut Glyphs,
+    c: &Context,
+    g: &mut G2d,
+) {
+    let top = HUD_HEIGHT as f64;
+    rectangle(PANEL_COLOR, [0.0, top, width, height - top], c.transform, g);
+
+    let mut y = top + 40.0;
+    draw_centered(&menu.title, y, width, glyphs, c, g);
+    y += LINE_HEIGHT;
+    for line in &menu.lines {
+        y += LINE_HEIGHT;
+        draw_centered(line, y, width, glyphs, c, g);
+    }
+    if !menu.lines.is_empty() {
+        y += LINE_HEIGHT;
+    }
+
+    for line in menu.item_lines() {
+        y += LINE_HEIGHT;
+        draw_centered(&line, y, width, glyphs, c, g);
+    }
+    draw_centered(&menu.hint, height - 30.0, width, glyphs, c, g);
+}
+
+/// Draws `text` horizontally centred on the window at height `y`.
+pub fn draw_centered(
+    text: &str,
+    y: f64,
+    width: f64,
+    glyphs: &mut Glyphs,
+    c: &Context,
+    g: &mut G2d,
+) {
+    let text_width = glyphs.width(FONT_SIZE, text).unwrap_or(0.0);
+    draw_text(text, [(width - text_width) / 2.0, y], glyphs, c, g);
+}
+
+fn draw_text(text: &str, [x, y]: [f64; 2], glyphs: &mut Glyphs, c: &Context, g: &mut G2d) {
+    if let Err(e) = Text::new_color(TEXT_COLOR, FONT_SIZE).draw(
+        text,
+        glyphs,
+        &c.draw_state,
+        c.transform.trans(x, y),
+        g,
+    ) {
+        // Drawing runs every frame, so the error would repeat with it
+        static REPORTED: Once = Once::new();
+        REPORTED.call_once(|| eprintln!("Error drawing text: {:?}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_snake_game::GameSettings;
+
+    #[test]
+    fn status_lines_show_the_game_and_the_best_score() {
+        let game = Game::with_settings(GameSettings::default(), 1);
+        let [top, bottom] = status_lines(&game, Some(12));
+        assert_eq!(top, "Score 0    Length 1    Best 12");
+        assert_eq!(bottom, "Time 0:00  Speed 10.0/s  Mode wrap normal");
+
+        let [top, _] = status_lines(&game, None);
+        assert!(!top.contains("Best"));
+    }
+
+    #[test]
+    fn menu_items_line_up_with_the_selection_marked() {
+        let menu = Menu {
+            title: String::new(),
+            lines: Vec::new(),
+            items: vec!["Play".to_string(), "Settings".to_string()],
+            selected: 1,
+            hint: String::new(),
+        };
+        assert_eq!(menu.item_lines(), ["  Play    ", "> Settings"]);
+
+        let empty = high_scores_menu(&[], "High scores".to_string());
+        assert_eq!(empty.lines, ["No scores yet"]);
+    }
+}
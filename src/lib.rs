@@ -0,0 +1,43 @@
+//! Headless snake engine.
+//!
+//! Everything needed to run a game of snake without a window: construct a
+//! [`Game`], feed it input with [`Game::change_direction`], advance it with
+//! [`Game::update`] or [`Game::step`] and read the board back through its
+//! accessors. The Piston binary in `main.rs` is just one front end on top.
+
+mod bot;
+mod food;
+mod game;
+mod grid;
+mod highscores;
+mod net;
+pub mod paths;
+mod protocol;
+mod render;
+mod replay;
+mod stats;
+mod versus;
+
+pub use bot::{Bot, BotKind, HamiltonianBot, PathfindingBot};
+pub use game::{
+    BoundaryMode, Difficulty, Direction, Game, GameSettings, Position, SpeedCurve,
+    DEFAULT_GRID_SIZE, MAX_CATCH_UP_TICKS, MAX_GRID_SIZE, MAX_QUEUED_INPUTS, MIN_GRID_SIZE,
+};
+pub use highscores::{HighScore, HighScoreTable, ScoreCategory, MAX_HIGH_SCORES, MAX_NAME_LEN};
+pub use net::{
+    Client, Host, HostSession, Spectator, DEFAULT_PORT, GUEST_PLAYER, HOST_PLAYER, MAX_NEWCOMERS,
+};
+pub use protocol::{
+    Message, Role, LONG_FRAME, MAX_FRAME_LEN, MAX_PEER_FRAME_LEN, PROTOCOL_MAGIC, PROTOCOL_VERSION,
+};
+pub use render::{
+    render_game, RenderStyle, CRASHED_ALPHA, GAME_OVER_TINT, MAX_RENDER_PIXELS, ROUND_OVER_TINT,
+    VICTORY_TINT, WALL_COLOR,
+};
+pub use replay::{Replay, ReplayInput, ReplayPlayer, REPLAY_EXTENSION, REPLAY_VERSION};
+pub use stats::{
+    GameStats, StatsFormat, StatsHistory, StatsRecord, StatsSummary, VersusOutcome, CSV_FILE_NAME,
+};
+pub use versus::{
+    PlayerSnapshot, RoundResult, Versus, VersusPlayer, VersusSnapshot, VERSUS_PLAYERS,
+};
@@ -1,302 +1,207 @@
-use piston_window::*;
-use rand::prelude::*;
-use rand::rng;
-use std::fs::File;
-use std::io::Write;
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
-use chrono::{DateTime, Local};
-
-const GRID_SIZE: u32 = 20;
-const CELL_SIZE: u32 = 25;
-const WIDTH: u32 = GRID_SIZE * CELL_SIZE;
-const HEIGHT: u32 = GRID_SIZE * CELL_SIZE;
-
-#[derive(Clone, Copy, PartialEq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Direction {
-    fn opposite(&self) -> Direction {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Down => Direction::Up,
-            Direction::Left => Direction::Right,
-            Direction::Right => Direction::Left,
-        }
-    }
-}
+mod app;
+mod board;
+mod cli;
+mod commands;
+mod config;
+mod hud;
+mod net_view;
+mod replay_view;
+mod tui;
 
-#[derive(Clone, Copy, PartialEq)]
-struct Position {
-    x: u32,
-    y: u32,
+use piston_window::*;
+use rust_snake_game::{paths, Client, Host, Replay, ReplayPlayer, Spectator, DEFAULT_PORT};
+use std::error::Error;
+use std::io;
+use std::process;
+
+use app::App;
+use board::Layout;
+use cli::{Cli, Command, HostArgs, JoinArgs, PlayArgs, ReplayArgs, SpectateArgs};
+use config::Config;
+use net_view::NetRound;
+use replay_view::ReplayView;
+
+// The engine keeps its own fixed tick rate, so these only set how often input
+// is sampled and how often the board is redrawn.
+const UPDATES_PER_SECOND: u64 = 120;
+const FRAMES_PER_SECOND: u64 = 60;
+
+fn open_window(
+    title: &str,
+    layout: &Layout,
+    exit_on_esc: bool,
+) -> Result<PistonWindow, Box<dyn Error>> {
+    WindowSettings::new(title, layout.window_size())
+        .exit_on_esc(exit_on_esc)
+        .build()
 }
 
-struct GameStats {
-    start_time: SystemTime,
-    time_played: Duration,
-    up_turns: u32,
-    down_turns: u32,
-    left_turns: u32,
-    right_turns: u32,
-    food_eaten: u32,
-    timestamp: u64,
+fn new_events() -> Events {
+    Events::new(
+        EventSettings::new()
+            .ups(UPDATES_PER_SECOND)
+            .max_fps(FRAMES_PER_SECOND),
+    )
 }
 
-impl GameStats {
-    fn new() -> Self {
-        let now = SystemTime::now();
-        let timestamp = now
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs();
-            
-        Self {
-            start_time: now,
-            time_played: Duration::from_secs(0),
-            up_turns: 0,
-            down_turns: 0,
-            left_turns: 0,
-            right_turns: 0,
-            food_eaten: 0,
-            timestamp,
-        }
-    }
-    
-    fn update(&mut self) {
-        self.time_played = SystemTime::now().duration_since(self.start_time).unwrap_or(Duration::from_secs(0));
-    }
-    
-    fn save_to_file(&self, final_score: u32) -> std::io::Result<()> {
-        // Format timestamp as human-readable date/time for filename
-        let dt: DateTime<Local> = self.start_time.into();
-        let filename = format!("{}_snake_game_stats.txt", dt.format("%Y%m%d_%H%M%S"));
-        
-        let mut file = File::create(filename)?;
-        
-        // Convert times to more readable format
-        let time_played_secs = self.time_played.as_secs();
-        let minutes = time_played_secs / 60;
-        let seconds = time_played_secs % 60;
-        
-        // Write stats to file
-        writeln!(file, "Snake Game Statistics")?;
-        writeln!(file, "=====================")?;
-        writeln!(file, "Game started at: {}", dt.format("%Y-%m-%d %H:%M:%S"))?;
-        writeln!(file, "Time played: {}m {}s", minutes, seconds)?;
-        writeln!(file, "Final score: {}", final_score)?;
-        writeln!(file, "Food eaten: {}", self.food_eaten)?;
-        writeln!(file, "")?;
-        writeln!(file, "Movement Statistics:")?;
-        writeln!(file, "  Up turns: {}", self.up_turns)?;
-        writeln!(file, "  Down turns: {}", self.down_turns)?;
-        writeln!(file, "  Left turns: {}", self.left_turns)?;
-        writeln!(file, "  Right turns: {}", self.right_turns)?;
-        writeln!(file, "")?;
-        writeln!(file, "Total turns: {}", self.up_turns + self.down_turns + self.left_turns + self.right_turns)?;
-        
-        Ok(())
+/// Reads the config file named on the command line, or the one in the config
+/// directory if there is one.
+fn load_config(cli: &Cli) -> Config {
+    let path = cli
+        .config
+        .clone()
+        .unwrap_or_else(paths::default_config_path);
+    if cli.config.is_some() || path.exists() {
+        Config::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error loading config {}: {}", path.display(), e);
+            process::exit(1);
+        })
+    } else {
+        Config::default()
     }
 }
 
-struct Game {
-    snake: Vec<Position>,
-    food: Position,
-    direction: Direction,
-    is_game_over: bool,
-    score: u32,
-    speed: f64,
-    last_update: f64,
-    stats: GameStats,
+/// Exits with the error if the command-line overrides left `config` invalid.
+fn check_config(config: &Config) {
+    if let Err(e) = config.validate() {
+        eprintln!("Error in settings: {}", e);
+        process::exit(1);
+    }
 }
 
-impl Game {
-    fn new() -> Game {
-        let mut rng = rng();
-        
-        Game {
-            snake: vec![Position { x: 10, y: 10 }],
-            food: Position {
-                x: rng.random_range(0..GRID_SIZE),
-                y: rng.random_range(0..GRID_SIZE),
-            },
-            direction: Direction::Right,
-            is_game_over: false,
-            score: 0,
-            speed: 0.1, // Time between updates in seconds
-            last_update: 0.0,
-            stats: GameStats::new(),
+fn main() {
+    let cli = Cli::parse_args();
+    let mut config = load_config(&cli);
+
+    match cli.command {
+        None => play(&cli.play, config),
+        Some(Command::Play(args)) => play(&args, config),
+        Some(Command::Replay(args)) => replay(&args, config),
+        Some(Command::Stats(args)) => {
+            if let Some(dir) = args.stats_dir.clone() {
+                config.stats.dir = Some(dir);
+            }
+            commands::stats(&args, &config.stats_dir());
         }
-    }
-
-    fn update(&mut self, dt: f64) -> bool {
-        if self.is_game_over {
-            return false;
+        Some(Command::Bot(args)) => {
+            config.override_board(&args.board);
+            check_config(&config);
+            commands::bot(&args, config.game_settings());
         }
-
-        // Update stats time played
-        self.stats.update();
-
-        self.last_update += dt;
-        if self.last_update < self.speed {
-            return false;
+        Some(Command::Bench(args)) => {
+            config.override_board(&args.board);
+            check_config(&config);
+            commands::bench(&args, config.game_settings());
         }
-        self.last_update = 0.0;
-
-        let head = self.snake[0];
-        let mut new_head = head;
-
-        match self.direction {
-            Direction::Up => new_head.y = (new_head.y + GRID_SIZE - 1) % GRID_SIZE,
-            Direction::Down => new_head.y = (new_head.y + 1) % GRID_SIZE,
-            Direction::Left => new_head.x = (new_head.x + GRID_SIZE - 1) % GRID_SIZE,
-            Direction::Right => new_head.x = (new_head.x + 1) % GRID_SIZE,
+        Some(Command::Host(args)) => {
+            config.override_board(&args.board);
+            check_config(&config);
+            host(&args, config);
         }
-
-        // Check collision with self
-        if self.snake.iter().skip(1).any(|p| p.x == new_head.x && p.y == new_head.y) {
-            self.is_game_over = true;
-            // Save stats to file when game is over
-            if let Err(e) = self.stats.save_to_file(self.score) {
-                eprintln!("Error saving stats: {}", e);
+        Some(Command::Join(args)) => join(&args, config),
+        Some(Command::Spectate(args)) => spectate(&args, config),
+        Some(Command::Render(args)) => {
+            if let Some(cell_size) = args.cell_size {
+                config.display.cell_size = cell_size;
             }
-            return true;
+            commands::render(&args, config.display.render_style());
         }
-
-        self.snake.insert(0, new_head);
-
-        if new_head.x == self.food.x && new_head.y == self.food.y {
-            // Ate food, grow snake and spawn new food
-            self.score += 1;
-            self.stats.food_eaten += 1;
-            self.speed = (0.1 - (self.score as f64 * 0.002)).max(0.05); // Speed up as score increases
-            self.spawn_food();
-        } else {
-            // Remove tail if no food was eaten
-            self.snake.pop();
-        }
-
-        true
     }
+}
 
-    fn spawn_food(&mut self) {
-        let mut rng = rng();
-        let mut new_food;
-
-        loop {
-            new_food = Position {
-                x: rng.random_range(0..GRID_SIZE),
-                y: rng.random_range(0..GRID_SIZE),
-            };
-
-            // Make sure food doesn't spawn on snake
-            if !self.snake.iter().any(|p| p.x == new_food.x && p.y == new_food.y) {
-                break;
-            }
-        }
-
-        self.food = new_food;
+fn replay(args: &ReplayArgs, mut config: Config) {
+    if let Some(cell_size) = args.cell_size {
+        config.display.cell_size = cell_size;
     }
-
-    fn change_direction(&mut self, new_direction: Direction) {
-        if new_direction.opposite() != self.direction {
-            // Update turn statistics
-            match new_direction {
-                Direction::Up => self.stats.up_turns += 1,
-                Direction::Down => self.stats.down_turns += 1,
-                Direction::Left => self.stats.left_turns += 1,
-                Direction::Right => self.stats.right_turns += 1,
-            }
-            
-            self.direction = new_direction;
-        }
+    let replay = Replay::load(&args.file).unwrap_or_else(|e| {
+        eprintln!("Error loading replay {}: {}", args.file.display(), e);
+        process::exit(1);
+    });
+    let view = ReplayView::new(ReplayPlayer::new(replay));
+    if args.tui {
+        run_tui(tui::replay(view, &config.display));
+    } else {
+        run_window(replay_view::run(view, &config));
     }
+}
 
-    fn reset(&mut self) {
-        // Save stats of the previous game
-        if let Err(e) = self.stats.save_to_file(self.score) {
-            eprintln!("Error saving stats: {}", e);
-        }
-        
-        // Create a new game
-        *self = Game::new();
+/// Opens the title screen, or goes straight into a game when the command
+/// line set up the board.
+fn play(args: &PlayArgs, mut config: Config) {
+    config.override_play(args);
+    check_config(&config);
+    let app = App::new(config, args.seed, args.board.is_set());
+    if args.tui {
+        run_tui(tui::play(app));
+    } else {
+        run_window(app.run());
     }
 }
 
-fn main() {
-    let mut window: PistonWindow = WindowSettings::new("Snake Game", [WIDTH, HEIGHT])
-        .exit_on_esc(true)
-        .build()
-        .unwrap();
-
-    let mut game = Game::new();
-    let mut events = Events::new(EventSettings::new().ups(60));
-
-    while let Some(e) = events.next(&mut window) {
-        if let Some(Button::Keyboard(key)) = e.press_args() {
-            match key {
-                Key::Up => game.change_direction(Direction::Up),
-                Key::Down => game.change_direction(Direction::Down),
-                Key::Left => game.change_direction(Direction::Left),
-                Key::Right => game.change_direction(Direction::Right),
-                Key::R if game.is_game_over => game.reset(),
-                _ => {}
-            }
-        }
+fn host(args: &HostArgs, config: Config) {
+    let settings = config.game_settings();
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let host = Host::bind(("0.0.0.0", args.port), settings, seed).unwrap_or_else(|e| {
+        eprintln!("Error listening on port {}: {}", args.port, e);
+        process::exit(1);
+    });
+    if let Ok(address) = host.local_addr() {
+        println!("Waiting for a player on port {}", address.port());
+    }
+    show_round(NetRound::host(host, settings), &config, args.tui);
+}
 
-        if let Some(update_args) = e.update_args() {
-            game.update(update_args.dt);
-        }
+fn join(args: &JoinArgs, config: Config) {
+    let address = with_default_port(&args.address);
+    let client = Client::connect(&address).unwrap_or_else(|e| {
+        eprintln!("Error joining {}: {}", address, e);
+        process::exit(1);
+    });
+    show_round(NetRound::join(client), &config, args.tui);
+}
 
-        window.draw_2d(&e, |c, g, _device| {
-            clear([0.0, 0.0, 0.0, 1.0], g);
+fn spectate(args: &SpectateArgs, config: Config) {
+    let address = with_default_port(&args.address);
+    println!("Waiting for the round on {} to start", address);
+    let spectator = Spectator::connect(&address).unwrap_or_else(|e| {
+        eprintln!("Error watching {}: {}", address, e);
+        process::exit(1);
+    });
+    show_round(NetRound::spectate(spectator), &config, args.tui);
+}
 
-            // Draw snake
-            for segment in &game.snake {
-                let x = segment.x * CELL_SIZE;
-                let y = segment.y * CELL_SIZE;
-                rectangle(
-                    [0.0, 1.0, 0.0, 1.0], // Green color
-                    [x as f64, y as f64, CELL_SIZE as f64, CELL_SIZE as f64],
-                    c.transform,
-                    g,
-                );
-            }
+fn show_round(round: NetRound, config: &Config, tui: bool) {
+    if tui {
+        run_tui(tui::net(round, config));
+    } else {
+        run_window(net_view::run(round, config));
+    }
+}
 
-            // Draw food
-            let food_x = game.food.x * CELL_SIZE;
-            let food_y = game.food.y * CELL_SIZE;
-            rectangle(
-                [1.0, 0.0, 0.0, 1.0], // Red color
-                [food_x as f64, food_y as f64, CELL_SIZE as f64, CELL_SIZE as f64],
-                c.transform,
-                g,
-            );
+/// Exits with the error if the terminal could not be used.
+fn run_tui(result: io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Error drawing in the terminal: {}", e);
+        process::exit(1);
+    }
+}
 
-            // Simple version without text rendering
-            // Just draw the score as blocks
-            for i in 0..game.score {
-                rectangle(
-                    [1.0, 1.0, 0.0, 1.0], // Yellow color
-                    [10.0 + (i as f64 * 15.0), 10.0, 10.0, 10.0],
-                    c.transform,
-                    g,
-                );
-            }
+/// Exits with the error if the window could not be opened.
+fn run_window(result: Result<(), Box<dyn Error>>) {
+    if let Err(e) = result {
+        eprintln!("Error opening the window: {}", e);
+        process::exit(1);
+    }
+}
 
-            // Draw game over indicator
-            if game.is_game_over {
-                rectangle(
-                    [1.0, 0.0, 0.0, 0.5], // Red with transparency
-                    [0.0, 0.0, WIDTH as f64, HEIGHT as f64],
-                    c.transform,
-                    g,
-                );
-            }
-        });
+/// Adds the default port to `address` when it has none.
+fn with_default_port(address: &str) -> String {
+    let has_port = address
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+    if has_port {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
     }
-}
\ No newline at end of file
+}
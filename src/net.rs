@@ -0,0 +1,688 @@
+use std::io::{self, BufReader, Write};
+use std::mem;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::{Direction, GameSettings};
+use crate::protocol::{Message, Role, MAX_FRAME_LEN, MAX_PEER_FRAME_LEN, PROTOCOL_VERSION};
+use crate::versus::{Versus, VERSUS_PLAYERS};
+
+/// Port used when none is given.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// How often [`Host::accept`] checks for a player while it waits.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long the host waits for a new connection to say hello.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most connections the host keeps waiting for a hello at once. Any more
+/// are hung up on straight away, so a flood of silent connections can't
+/// pile up reader threads.
+pub const MAX_NEWCOMERS: usize = 16;
+
+/// How long a spectator's writer thread waits on them when they aren't
+/// reading before it hangs up.
+const SPECTATOR_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most messages queued for a spectator. One who falls further behind than
+/// this is dropped.
+const SPECTATOR_BACKLOG: usize = 1024;
+
+/// How long the host waits on a guest who has stopped reading before it
+/// gives up on the round, so the game loop can't block on them forever.
+const GUEST_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Index of the host's own snake; the player who joins gets the other one.
+pub const HOST_PLAYER: usize = 0;
+pub const GUEST_PLAYER: usize = 1;
+
+/// A versus round waiting for a second player to join over TCP.
+/// Spectators who connect before the player are let in when the round
+/// starts.
+pub struct Host {
+    listener: TcpListener,
+    settings: GameSettings,
+    seed: u64,
+    spectators: Vec<TcpStream>,
+    /// Connections that haven't said hello yet
+    newcomers: Vec<Newcomer>,
+}
+
+impl Host {
+    /// Listens on `address` for a player to join a round played with
+    /// `settings` and `seed`.
+    pub fn bind<A: ToSocketAddrs>(
+        address: A,
+        settings: GameSettings,
+        seed: u64,
+    ) -> io::Result<Self> {
+        settings
+            .validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            settings,
+            seed,
+            spectators: Vec::new(),
+            newcomers: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Number of spectators waiting for the round to start.
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// Waits for a player to connect and starts the round. Connections
+    /// that fail to shake hands are dropped and the wait goes on.
+    pub fn accept(&mut self) -> io::Result<HostSession> {
+        loop {
+            if let Some(session) = self.try_accept()? {
+                return Ok(session);
+            }
+            thread::sleep(ACCEPT_INTERVAL);
+        }
+    }
+
+    /// Like [`Host::accept`], but returns `Ok(None)` straight away when no
+    /// player has said hello yet.
+    pub fn try_accept(&mut self) -> io::Result<Option<HostSession>> {
+        let accepted = accept_newcomers(&self.listener, &mut self.newcomers);
+        let mut greeted = greeted(&mut self.newcomers).into_iter();
+        for (role, newcomer) in greeted.by_ref() {
+            match role {
+                Role::Spectator => self.spectators.extend(newcomer.into_spectator()),
+                Role::Player => {
+                    if let Some(mut session) = self.start(newcomer) {
+                        // Anyone who said hello at the same time is let in
+                        // or turned away by the round
+                        for (role, newcomer) in greeted {
+                            session.welcome(role, newcomer);
+                        }
+                        return Ok(Some(session));
+                    }
+                }
+            }
+        }
+        accepted.map(|()| None)
+    }
+
+    /// Sends the round to a player who has said hello and starts it, or
+    /// `None` if they have gone away.
+    fn start(&mut self, mut newcomer: Newcomer) -> Option<HostSession> {
+        let welcome = Message::Welcome {
+            player: GUEST_PLAYER as u8,
+            seed: self.seed,
+            settings: self.settings,
+        };
+        let sent = newcomer
+            .stream
+            .set_write_timeout(Some(GUEST_WRITE_TIMEOUT))
+            .and_then(|()| welcome.write_to(&mut newcomer.stream));
+        let listener = match (sent, self.listener.try_clone()) {
+            (Ok(()), Ok(listener)) => listener,
+            _ => {
+                newcomer.hang_up();
+                return None;
+            }
+        };
+
+        let mut session = HostSession {
+            stream: newcomer.stream,
+            incoming: newcomer.incoming,
+            versus: Versus::with_settings(self.settings, self.seed),
+            pending: Vec::new(),
+            listener,
+            spectators: Vec::new(),
+            joining: mem::take(&mut self.spectators),
+            newcomers: mem::take(&mut self.newcomers),
+        };
+        session.admit_spectators();
+        Some(session)
+    }
+}
+
+impl Drop for Host {
+    fn drop(&mut self) {
+        for spectator in &self.spectators {
+            hang_up(spectator);
+        }
+        for newcomer in self.newcomers.drain(..) {
+            newcomer.hang_up();
+        }
+    }
+}
+
+/// The host's side of a round in progress. The host runs the round on its
+/// own clock; the guest only sends turns. Before every tick the host sends
+/// the turns queued since the last one, so the guest can replay them on its
+/// own copy of the round and stay in lockstep.
+///
+/// Spectators can join at any point. Each gets a snapshot of the round and
+/// then the same ticks as the guest.
+pub struct HostSession {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<Message>>,
+    versus: Versus,
+    /// Turns queued since the last tick, sent with the next one
+    pending: Vec<(u8, Direction)>,
+    listener: TcpListener,
+    spectators: Vec<SpectatorFeed>,
+    /// Spectators waiting for a moment with no turns pending, when a
+    /// snapshot followed by the next tick tells the whole story
+    joining: Vec<TcpStream>,
+    /// Connections that haven't said hello yet
+    newcomers: Vec<Newcomer>,
+}
+
+impl HostSession {
+    pub fn versus(&self) -> &Versus {
+        &self.versus
+    }
+
+    /// Number of spectators watching.
+    pub fn spectators(&self) -> usize {
+        self.spectators.len() + self.joining.len()
+    }
+
+    /// Queues a turn for the host's snake.
+    pub fn turn(&mut self, direction: Direction) {
+        if self.versus.change_direction(HOST_PLAYER, direction) {
+            self.pending.push((HOST_PLAYER as u8, direction));
+        }
+    }
+
+    /// Lets in any new spectators and queues the turns the guest has sent
+    /// so far. Never waits on a connection that hasn't said hello yet.
+    pub fn poll(&mut self) -> io::Result<()> {
+        // Someone who can't watch doesn't get to stop the round
+        let _ = accept_newcomers(&self.listener, &mut self.newcomers);
+        for (role, newcomer) in greeted(&mut self.newcomers) {
+            self.welcome(role, newcomer);
+        }
+        // Before hearing from the guest, so spectators still get in to see
+        // how the round ended once the guest has gone
+        self.admit_spectators();
+
+        while let Some(message) = receive(&self.incoming)? {
+            match message {
+                Message::Turn { direction } => {
+                    if self.versus.change_direction(GUEST_PLAYER, direction) {
+                        self.pending.push((GUEST_PLAYER as u8, direction));
+                    }
+                }
+                message => return Err(unexpected(&message)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets a spectator who has said hello in once turns are settled, and
+    /// turns away anyone else who wants to play.
+    fn welcome(&mut self, role: Role, newcomer: Newcomer) {
+        match role {
+            Role::Spectator => self.joining.extend(newcomer.into_spectator()),
+            Role::Player => newcomer.reject("this round already has two players"),
+        }
+    }
+
+    /// Advances the round by `dt` seconds like [`Versus::update`], sending
+    /// every tick to the guest and the spectators. Returns `true` when the
+    /// board changed.
+    pub fn update(&mut self, dt: f64) -> io::Result<bool> {
+        self.poll()?;
+
+        let mut ticks = Vec::new();
+        let pending = &mut self.pending;
+        let ticked = self.versus.update_with(dt, |versus| {
+            ticks.push(Message::Tick {
+                tick: versus.tick() + 1,
+                turns: mem::take(pending),
+            })
+        });
+        for tick in ticks {
+            self.broadcast(&tick)?;
+        }
+        self.admit_spectators();
+        Ok(ticked)
+    }
+
+    /// Runs exactly one tick regardless of timing and sends it on. Returns
+    /// `false` if the round had already finished.
+    pub fn step(&mut self) -> io::Result<bool> {
+        self.poll()?;
+        let ticked = !self.versus.is_finished();
+        if ticked {
+            let tick = Message::Tick {
+                tick: self.versus.tick() + 1,
+                turns: mem::take(&mut self.pending),
+            };
+            self.broadcast(&tick)?;
+            self.versus.step();
+        }
+        self.admit_spectators();
+        Ok(ticked)
+    }
+
+    /// Sends `message` to the guest and every spectator, hanging up on
+    /// spectators who have gone away or fallen behind. A guest who has done
+    /// either is hung up on too, and ends the round with an error.
+    fn broadcast(&mut self, message: &Message) -> io::Result<()> {
+        let frame = frame(message)?;
+        self.spectators.retain(|spectator| spectator.send(&frame));
+        self.stream.write_all(&frame).map_err(|e| {
+            // The guest may have been cut off halfway through a message, so
+            // there is no picking the round up again
+            hang_up(&self.stream);
+            match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the other player stopped keeping up with the round",
+                ),
+                _ => e,
+            }
+        })
+    }
+
+    /// Sends joining spectators a snapshot, unless turns are pending: those
+    /// are already in the round's queues and would be played twice once the
+    /// next tick brings them.
+    fn admit_spectators(&mut self) {
+        if self.joining.is_empty() || !self.pending.is_empty() {
+            return;
+        }
+        let snapshot = frame(&Message::Snapshot {
+            round: Box::new(self.versus.snapshot()),
+        });
+        for spectator in self.joining.drain(..) {
+            match &snapshot {
+                Ok(snapshot) => self.spectators.extend(
+                    SpectatorFeed::start(spectator).filter(|spectator| spectator.send(snapshot)),
+                ),
+                Err(_) => hang_up(&spectator),
+            }
+        }
+    }
+}
+
+impl Drop for HostSession {
+    fn drop(&mut self) {
+        hang_up(&self.stream);
+        for spectator in &self.spectators {
+            hang_up(&spectator.stream);
+        }
+        for spectator in &self.joining {
+            hang_up(spectator);
+        }
+        for newcomer in self.newcomers.drain(..) {
+            newcomer.hang_up();
+        }
+    }
+}
+
+/// A spectator watching the round. Messages are written on a thread of its
+/// own, so a spectator who is slow to read never holds up the tick loop.
+struct SpectatorFeed {
+    stream: TcpStream,
+    outgoing: SyncSender<Arc<[u8]>>,
+}
+
+impl SpectatorFeed {
+    /// Starts the writer thread, or hangs up if it can't be started.
+    fn start(stream: TcpStream) -> Option<Self> {
+        let mut writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => {
+                hang_up(&stream);
+                return None;
+            }
+        };
+        let (outgoing, frames) = mpsc::sync_channel::<Arc<[u8]>>(SPECTATOR_BACKLOG);
+        thread::spawn(move || {
+            for frame in frames {
+                if writer.write_all(&frame).is_err() {
+                    hang_up(&writer);
+                    break;
+                }
+            }
+        });
+        Some(Self { stream, outgoing })
+    }
+
+    /// Queues `frame` for the spectator. Returns `false`, having hung up,
+    /// if they have gone away or fallen more than [`SPECTATOR_BACKLOG`]
+    /// messages behind.
+    fn send(&self, frame: &Arc<[u8]>) -> bool {
+        let queued = self.outgoing.try_send(Arc::clone(frame)).is_ok();
+        if !queued {
+            hang_up(&self.stream);
+        }
+        queued
+    }
+}
+
+/// A connection that hasn't said hello yet. Its hello is read on its own
+/// reader thread, so a peer that connects and then says nothing can't
+/// hold up the round; it is hung up on once [`HANDSHAKE_TIMEOUT`] passes.
+struct Newcomer {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<Message>>,
+    deadline: Instant,
+}
+
+impl Newcomer {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            incoming: spawn_reader(&stream, MAX_PEER_FRAME_LEN)?,
+            stream,
+            deadline: Instant::now() + HANDSHAKE_TIMEOUT,
+        })
+    }
+
+    /// Checks for the newcomer's hello without waiting for it, and that it
+    /// speaks our protocol version. A peer on another version is told why
+    /// before being hung up on.
+    fn greet(self) -> Greeting {
+        match self.incoming.try_recv() {
+            Ok(Ok(Message::Hello { version, role })) if version == PROTOCOL_VERSION => {
+                Greeting::Hello(role, self)
+            }
+            Ok(Ok(Message::Hello { version, .. })) => {
+                self.reject(&format!(
+                    "protocol version {} is not supported (the host speaks {})",
+                    version, PROTOCOL_VERSION
+                ));
+                Greeting::Gone
+            }
+            Err(TryRecvError::Empty) if Instant::now() < self.deadline => Greeting::Waiting(self),
+            _ => {
+                self.hang_up();
+                Greeting::Gone
+            }
+        }
+    }
+
+    /// The stream to send the round to, set up so a spectator who stops
+    /// reading can't tie up its writer thread for good.
+    fn into_spectator(self) -> Option<TcpStream> {
+        match self.stream.set_write_timeout(Some(SPECTATOR_WRITE_TIMEOUT)) {
+            Ok(()) => Some(self.stream),
+            Err(_) => {
+                self.hang_up();
+                None
+            }
+        }
+    }
+
+    /// Tells the newcomer why it can't come in, then hangs up.
+    fn reject(mut self, reason: &str) {
+        let _ = Message::Reject {
+            reason: reason.to_string(),
+        }
+        .write_to(&mut self.stream);
+        self.hang_up();
+    }
+
+    /// Hangs up without saying why.
+    fn hang_up(self) {
+        hang_up(&self.stream);
+    }
+}
+
+enum Greeting {
+    /// Nothing yet, but there is still time
+    Waiting(Newcomer),
+    /// Said hello and asked for a role
+    Hello(Role, Newcomer),
+    /// Went away, ran out of time, said something else or speaks another
+    /// version, and has been hung up on
+    Gone,
+}
+
+/// Takes every connection waiting on `listener` as a newcomer, without
+/// blocking, up to [`MAX_NEWCOMERS`].
+fn accept_newcomers(listener: &TcpListener, newcomers: &mut Vec<Newcomer>) -> io::Result<()> {
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) if newcomers.len() >= MAX_NEWCOMERS => hang_up(&stream),
+            // A connection that can't be set up is just dropped
+            Ok((stream, _)) => newcomers.extend(Newcomer::new(stream).ok()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Takes every newcomer who has said hello out of `newcomers`, with the
+/// role they asked for, and drops those who have given up or run out of
+/// time.
+fn greeted(newcomers: &mut Vec<Newcomer>) -> Vec<(Role, Newcomer)> {
+    let mut greeted = Vec::new();
+    for newcomer in mem::take(newcomers) {
+        match newcomer.greet() {
+            Greeting::Waiting(newcomer) => newcomers.push(newcomer),
+            Greeting::Hello(role, newcomer) => greeted.push((role, newcomer)),
+            Greeting::Gone => {}
+        }
+    }
+    greeted
+}
+
+/// A player's side of a round hosted elsewhere. The round here only moves
+/// when the host says so.
+pub struct Client {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<Message>>,
+    versus: Versus,
+    player: usize,
+}
+
+impl Client {
+    /// Connects to a host and waits to be let in.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let mut stream = connect(address, Role::Player)?;
+        match Message::read_from(&mut stream)? {
+            Message::Welcome {
+                player,
+                seed,
+                settings,
+            } if usize::from(player) == GUEST_PLAYER => Ok(Self {
+                incoming: spawn_reader(&stream, MAX_FRAME_LEN)?,
+                stream,
+                versus: Versus::with_settings(settings, seed),
+                player: usize::from(player),
+            }),
+            Message::Reject { reason } => Err(refused(reason)),
+            message => Err(unexpected(&message)),
+        }
+    }
+
+    pub fn versus(&self) -> &Versus {
+        &self.versus
+    }
+
+    /// Index of this player's snake in [`Versus::players`].
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// Sends a turn for this player's snake to the host. It only takes
+    /// effect once the host sends it back with a tick.
+    pub fn turn(&mut self, direction: Direction) -> io::Result<()> {
+        Message::Turn { direction }.write_to(&mut self.stream)
+    }
+
+    /// Counts `dt` seconds of play and runs every tick the host has sent so
+    /// far. Returns `true` when the board changed.
+    pub fn update(&mut self, dt: f64) -> io::Result<bool> {
+        follow(&self.incoming, &mut self.versus, dt)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        hang_up(&self.stream);
+    }
+}
+
+/// A read-only view of a round hosted elsewhere.
+pub struct Spectator {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<Message>>,
+    versus: Versus,
+}
+
+impl Spectator {
+    /// Connects to a host and waits for the round as it stands, which only
+    /// comes once both players are in.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let mut stream = connect(address, Role::Spectator)?;
+        match Message::read_from(&mut stream)? {
+            Message::Snapshot { round } => Ok(Self {
+                incoming: spawn_reader(&stream, MAX_FRAME_LEN)?,
+                stream,
+                versus: Versus::from_snapshot(&round)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            }),
+            Message::Reject { reason } => Err(refused(reason)),
+            message => Err(unexpected(&message)),
+        }
+    }
+
+    pub fn versus(&self) -> &Versus {
+        &self.versus
+    }
+
+    /// Catches up with the host, see [`Client::update`].
+    pub fn update(&mut self, dt: f64) -> io::Result<bool> {
+        follow(&self.incoming, &mut self.versus, dt)
+    }
+}
+
+impl Drop for Spectator {
+    fn drop(&mut self) {
+        hang_up(&self.stream);
+    }
+}
+
+/// Opens a connection to a host and says hello.
+fn connect<A: ToSocketAddrs>(address: A, role: Role) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    Message::Hello {
+        version: PROTOCOL_VERSION,
+        role,
+    }
+    .write_to(&mut stream)?;
+    Ok(stream)
+}
+
+/// Plays every tick that has arrived on `versus`, counting `dt` seconds of
+/// play first. Returns `true` when the board changed.
+fn follow(
+    incoming: &Receiver<io::Result<Message>>,
+    versus: &mut Versus,
+    dt: f64,
+) -> io::Result<bool> {
+    if !versus.is_finished() {
+        versus.add_play_time(dt);
+    }
+
+    let mut ticked = false;
+    while let Some(message) = receive(incoming)? {
+        match message {
+            Message::Tick { tick, turns } => {
+                if tick != versus.tick() + 1 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "out of step with the host: got tick {} after tick {}",
+                            tick,
+                            versus.tick()
+                        ),
+                    ));
+                }
+                for (player, direction) in turns {
+                    if usize::from(player) >= VERSUS_PLAYERS {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("turn for unknown player {}", player),
+                        ));
+                    }
+                    versus.change_direction(usize::from(player), direction);
+                }
+                ticked |= versus.step();
+            }
+            message => return Err(unexpected(&message)),
+        }
+    }
+    Ok(ticked)
+}
+
+/// Reads messages of up to `max_len` bytes from `stream` on a thread of its
+/// own, so the game loop can pick them up without blocking. The channel ends
+/// after the first error, which includes the connection closing.
+fn spawn_reader(stream: &TcpStream, max_len: usize) -> io::Result<Receiver<io::Result<Message>>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let message = Message::read_limited(&mut reader, max_len);
+        let failed = message.is_err();
+        if sender.send(message).is_err() || failed {
+            break;
+        }
+    });
+    Ok(receiver)
+}
+
+/// `message` as it goes on the wire, to send to several peers.
+fn frame(message: &Message) -> io::Result<Arc<[u8]>> {
+    let mut frame = Vec::new();
+    message.write_to(&mut frame)?;
+    Ok(frame.into())
+}
+
+/// Closes `stream` both ways, which also ends the reader thread started on
+/// it by [`spawn_reader`], as that holds a clone of the socket.
+fn hang_up(stream: &TcpStream) {
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// The next message that has arrived, `None` if there is none yet, or the
+/// error that ended the connection once every message before it was taken.
+fn receive(incoming: &Receiver<io::Result<Message>>) -> io::Result<Option<Message>> {
+    match incoming.try_recv() {
+        Ok(message) => message.map(Some),
+        Err(TryRecvError::Empty) => Ok(None),
+        Err(TryRecvError::Disconnected) => Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "connection closed",
+        )),
+    }
+}
+
+fn refused(reason: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionRefused,
+        format!("the host refused to let us in: {}", reason),
+    )
+}
+
+fn unexpected(message: &Message) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message {:?}", message),
+    )
+}
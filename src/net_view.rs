@@ -0,0 +1,261 @@
+use piston_window::*;
+use rust_snake_game::{
+    Client, Direction, GameSettings, Host, HostSession, RoundResult, Spectator, Versus, HOST_PLAYER,
+};
+use std::error::Error;
+use std::io;
+
+use crate::app::versus_summary;
+use crate::board::{self, Layout};
+use crate::config::Config;
+use crate::hud::{self, Menu};
+use crate::{new_events, open_window};
+
+/// This window's side of a networked round.
+enum Peer {
+    /// Hosting, with nobody joined yet
+    Waiting(Host),
+    Host(HostSession),
+    Guest(Client),
+    Spectator(Spectator),
+}
+
+impl Peer {
+    /// The round, once both players are in.
+    fn versus(&self) -> Option<&Versus> {
+        match self {
+            Peer::Waiting(_) => None,
+            Peer::Host(session) => Some(session.versus()),
+            Peer::Guest(client) => Some(client.versus()),
+            Peer::Spectator(spectator) => Some(spectator.versus()),
+        }
+    }
+
+    /// Index of the snake steered from this window, if any.
+    fn player(&self) -> Option<usize> {
+        match self {
+            Peer::Waiting(_) | Peer::Host(_) => Some(HOST_PLAYER),
+            Peer::Guest(client) => Some(client.player()),
+            Peer::Spectator(_) => None,
+        }
+    }
+
+    /// Number of spectators watching, as far as this window knows.
+    fn spectators(&self) -> Option<usize> {
+        match self {
+            Peer::Waiting(host) => Some(host.spectators()),
+            Peer::Host(session) => Some(session.spectators()),
+            Peer::Guest(_) | Peer::Spectator(_) => None,
+        }
+    }
+
+    fn turn(&mut self, direction: Direction) -> io::Result<()> {
+        match self {
+            Peer::Waiting(_) | Peer::Spectator(_) => Ok(()),
+            Peer::Host(session) => {
+                session.turn(direction);
+                Ok(())
+            }
+            Peer::Guest(client) => client.turn(direction),
+        }
+    }
+
+    fn update(&mut self, dt: f64) -> io::Result<()> {
+        match self {
+            Peer::Waiting(host) => {
+                if let Some(session) = host.try_accept()? {
+                    *self = Peer::Host(session);
+                }
+            }
+            Peer::Host(session) => {
+                session.update(dt)?;
+            }
+            Peer::Guest(client) => {
+                client.update(dt)?;
+            }
+            Peer::Spectator(spectator) => {
+                spectator.update(dt)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One side of a networked round, as shown in a window or a terminal.
+pub struct NetRound {
+    peer: Peer,
+    settings: GameSettings,
+    /// Port the host listens on, shown while waiting for a player
+    port: Option<u16>,
+    /// Why the connection was lost, once it has been
+    error: Option<String>,
+    /// Why the last connection couldn't be taken while hosting
+    accept_error: Option<String>,
+}
+
+impl NetRound {
+    /// Waits for a player to join `host`, then plays the round with the
+    /// local player on either set of turn keys.
+    pub fn host(host: Host, settings: GameSettings) -> Self {
+        let port = host.local_addr().map(|address| address.port()).ok();
+        Self::new(Peer::Waiting(host), settings, port)
+    }
+
+    /// Plays a round hosted elsewhere.
+    pub fn join(client: Client) -> Self {
+        let settings = *client.versus().settings();
+        Self::new(Peer::Guest(client), settings, None)
+    }
+
+    /// Watches a round hosted elsewhere.
+    pub fn spectate(spectator: Spectator) -> Self {
+        let settings = *spectator.versus().settings();
+        Self::new(Peer::Spectator(spectator), settings, None)
+    }
+
+    fn new(peer: Peer, settings: GameSettings, port: Option<u16>) -> Self {
+        Self {
+            peer,
+            settings,
+            port,
+            error: None,
+            accept_error: None,
+        }
+    }
+
+    pub fn settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    /// The round, once both players are in.
+    pub fn versus(&self) -> Option<&Versus> {
+        self.peer.versus()
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.peer {
+            Peer::Waiting(_) | Peer::Host(_) => "Snake Game (host)",
+            Peer::Guest(_) => "Snake Game (online)",
+            Peer::Spectator(_) => "Snake Game (spectating)",
+        }
+    }
+
+    /// Steers the local snake with either set of turn keys.
+    pub fn on_key(&mut self, key: Key, config: &Config) {
+        if let Some((_, direction)) = config.keys.versus_turn(key) {
+            if let Err(e) = self.peer.turn(direction) {
+                self.error.get_or_insert(e.to_string());
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        let finished = self.versus().is_some_and(Versus::is_finished);
+        // The host keeps going after the round ends so late spectators
+        // still get to see how it finished
+        let keep_going = !finished || matches!(self.peer, Peer::Host(_));
+        if self.error.is_none() && keep_going {
+            match self.peer.update(dt) {
+                // Likely out of file descriptors for a moment; keep waiting
+                Err(e) if matches!(self.peer, Peer::Waiting(_)) => {
+                    self.accept_error = Some(format!("Error accepting a connection: {}", e));
+                }
+                Err(e) if !finished => self.error = Some(e.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Who is playing and who is watching, for the bottom of the board.
+    pub fn status(&self) -> String {
+        let mut status = match self.peer.player() {
+            Some(player) => format!("You are player {}", player + 1),
+            None => "Spectating".to_string(),
+        };
+        if let Some(spectators) = self.peer.spectators().filter(|&n| n > 0) {
+            status.push_str(&format!(" - {} watching", spectators));
+        }
+        status
+    }
+
+    /// What to show over the board: the connection error, the wait for a
+    /// player or the result. `None` while the round is on.
+    pub fn menu(&self) -> Option<Menu> {
+        let mut lines = Vec::new();
+        let title = match (self.versus(), &self.error) {
+            (_, Some(error)) => {
+                lines.push(error.clone());
+                "Connection lost".to_string()
+            }
+            (None, None) => {
+                if let Some(port) = self.port {
+                    lines.push(format!("Listening on port {}", port));
+                }
+                lines.extend(self.accept_error.clone());
+                let hint = "Esc to stop hosting".to_string();
+                return Some(menu("Waiting for a player".to_string(), lines, hint));
+            }
+            (Some(versus), None) => {
+                let title = match (versus.result()?, self.peer.player()) {
+                    (RoundResult::Winner(winner), None) => format!("Player {} wins", winner + 1),
+                    (RoundResult::Winner(winner), Some(player)) if winner == player => {
+                        "You win!".to_string()
+                    }
+                    (RoundResult::Winner(_), Some(_)) => "You lose".to_string(),
+                    (RoundResult::Draw, _) => "Draw".to_string(),
+                };
+                lines = versus_summary(versus);
+                lines.push(self.status());
+                title
+            }
+        };
+        Some(menu(title, lines, "Esc to quit".to_string()))
+    }
+}
+
+fn menu(title: String, lines: Vec<String>, hint: String) -> Menu {
+    Menu {
+        title,
+        lines,
+        items: Vec::new(),
+        selected: 0,
+        hint,
+    }
+}
+
+pub fn run(mut round: NetRound, config: &Config) -> Result<(), Box<dyn Error>> {
+    let layout = Layout::new(round.settings(), &config.display);
+    let [width, height] = layout.window_size().map(f64::from);
+    let mut window = open_window(round.title(), &layout, true)?;
+    let mut glyphs = hud::load_font(&mut window);
+    let mut events = new_events();
+
+    while let Some(e) = events.next(&mut window) {
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            round.on_key(key, config);
+        }
+        if let Some(update_args) = e.update_args() {
+            round.update(update_args.dt);
+        }
+
+        window.draw_2d(&e, |c, g, device| {
+            clear(config.display.background.0, g);
+            if let Some(versus) = round.versus() {
+                board::draw_versus(versus, &layout, &config.display, &c, g);
+            }
+            let Some(glyphs) = glyphs.as_mut() else {
+                return;
+            };
+
+            if let Some(versus) = round.versus() {
+                hud::draw_versus(versus, glyphs, width, &c, g);
+            }
+            match round.menu() {
+                Some(menu) => hud::draw_menu(&menu, width, height, glyphs, &c, g),
+                None => hud::draw_centered(&round.status(), height - 12.0, width, glyphs, &c, g),
+            }
+            glyphs.factory.encoder.flush(device);
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+/// Directory name used under the platform data directory.
+pub const APP_DIR_NAME: &str = "rust-snake-game";
+
+/// Per-user data directory for the game, e.g. `~/.local/share/rust-snake-game`
+/// on Linux. Falls back to the working directory on platforms without one.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Per-user config directory for the game, e.g. `~/.config/rust-snake-game`
+/// on Linux. Falls back to the working directory on platforms without one.
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `config.toml` in the per-user config directory.
+pub fn default_config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Where stats files go unless told otherwise.
+pub fn default_stats_dir() -> PathBuf {
+    data_dir().join("stats")
+}
+
+/// Where replays of finished games go.
+pub fn default_replay_dir() -> PathBuf {
+    data_dir().join("replays")
+}
@@ -0,0 +1,443 @@
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::game::{BoundaryMode, Difficulty, Direction, GameSettings, Position, SpeedCurve};
+use crate::versus::{PlayerSnapshot, RoundResult, VersusSnapshot, VERSUS_PLAYERS};
+
+/// Bumped whenever a message changes shape or meaning. Peers on different
+/// versions refuse each other during the handshake.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// Sent at the start of every [`Message::Hello`] so stray connections from
+/// other programs are turned away.
+pub const PROTOCOL_MAGIC: [u8; 4] = *b"SNAK";
+
+/// Largest frame either side will read, enough for a snapshot of two snakes
+/// filling the largest board.
+pub const MAX_FRAME_LEN: usize = 16 << 20;
+
+/// Largest frame the host reads from the connections it accepts. They only
+/// ever send a hello and then turns, so a stranger can't make the host set
+/// aside [`MAX_FRAME_LEN`] bytes just by connecting.
+pub const MAX_PEER_FRAME_LEN: usize = 1 << 10;
+
+/// Marks a frame whose length doesn't fit in the `u16` every frame starts
+/// with. Short frames, which includes every handshake message, are laid out
+/// as they were in version 1, so older peers can still be told to upgrade.
+pub const LONG_FRAME: u16 = u16::MAX;
+
+/// What a connection to the host is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Steers the second snake
+    Player,
+    /// Only watches
+    Spectator,
+}
+
+/// Everything the host and a player say to each other.
+///
+/// On the wire a message is a frame: its length as a big-endian `u16`, then
+/// a kind byte, then the fields in order. Frames of [`LONG_FRAME`] bytes or
+/// more give [`LONG_FRAME`] as the length and follow it with the real length
+/// as a `u32`. Integers are big-endian, a
+/// direction is one byte (up, down, left, right as 0 to 3) and a position is
+/// two `u16`s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// Player or spectator to host, first thing after connecting
+    Hello { version: u16, role: Role },
+    /// Host to player: the round about to be played, and which snake is
+    /// theirs
+    Welcome {
+        player: u8,
+        seed: u64,
+        settings: GameSettings,
+    },
+    /// Host to player, instead of [`Message::Welcome`], before hanging up
+    Reject { reason: String },
+    /// Player to host: a turn for the player's snake
+    Turn { direction: Direction },
+    /// Host to players and spectators: the turns queued since the last
+    /// tick, in order, followed by tick number `tick`
+    Tick {
+        tick: u64,
+        turns: Vec<(u8, Direction)>,
+    },
+    /// Host to spectator, instead of [`Message::Welcome`]: the round as it
+    /// stands, to follow with the ticks after it
+    Snapshot { round: Box<VersusSnapshot> },
+}
+
+const KIND_HELLO: u8 = 1;
+const KIND_WELCOME: u8 = 2;
+const KIND_REJECT: u8 = 3;
+const KIND_TURN: u8 = 4;
+const KIND_TICK: u8 = 5;
+const KIND_SNAPSHOT: u8 = 6;
+
+impl Message {
+    /// Writes the message as one frame.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut payload = Vec::new();
+        match self {
+            Message::Hello { version, role } => {
+                payload.push(KIND_HELLO);
+                payload.extend_from_slice(&PROTOCOL_MAGIC);
+                payload.extend_from_slice(&version.to_be_bytes());
+                payload.push(match role {
+                    Role::Player => 0,
+                    Role::Spectator => 1,
+                });
+            }
+            Message::Welcome {
+                player,
+                seed,
+                settings,
+            } => {
+                payload.push(KIND_WELCOME);
+                payload.push(*player);
+                payload.extend_from_slice(&seed.to_be_bytes());
+                write_settings(&mut payload, settings);
+            }
+            Message::Reject { reason } => {
+                payload.push(KIND_REJECT);
+                payload.extend_from_slice(reason.as_bytes());
+            }
+            Message::Turn { direction } => {
+                payload.push(KIND_TURN);
+                payload.push(direction_byte(*direction));
+            }
+            Message::Tick { tick, turns } => {
+                payload.push(KIND_TICK);
+                payload.extend_from_slice(&tick.to_be_bytes());
+                payload.push(u8::try_from(turns.len()).map_err(|_| invalid("too many turns"))?);
+                for (player, direction) in turns {
+                    payload.push(*player);
+                    payload.push(direction_byte(*direction));
+                }
+            }
+            Message::Snapshot { round } => {
+                payload.push(KIND_SNAPSHOT);
+                write_snapshot(&mut payload, round);
+            }
+        }
+        if payload.len() > MAX_FRAME_LEN {
+            return Err(invalid("message too long"));
+        }
+
+        match u16::try_from(payload.len()) {
+            Ok(len) if len < LONG_FRAME => writer.write_all(&len.to_be_bytes())?,
+            _ => {
+                writer.write_all(&LONG_FRAME.to_be_bytes())?;
+                writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+            }
+        }
+        writer.write_all(&payload)?;
+        writer.flush()
+    }
+
+    /// Reads one frame, waiting for it if the reader blocks.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Message> {
+        Message::read_limited(reader, MAX_FRAME_LEN)
+    }
+
+    /// Like [`Message::read_from`], but refuses frames longer than
+    /// `max_len` before reading them.
+    pub fn read_limited<R: Read>(reader: &mut R, max_len: usize) -> io::Result<Message> {
+        let mut len = [0; 2];
+        reader.read_exact(&mut len).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed")
+            }
+            _ => e,
+        })?;
+        let len = match u16::from_be_bytes(len) {
+            LONG_FRAME => {
+                let mut len = [0; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        if len > max_len.min(MAX_FRAME_LEN) {
+            return Err(invalid("frame too long"));
+        }
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload)?;
+
+        let mut fields = Fields(&payload);
+        let message = match fields.u8()? {
+            KIND_HELLO => {
+                if fields.take(PROTOCOL_MAGIC.len())? != PROTOCOL_MAGIC {
+                    return Err(invalid("not a snake game peer"));
+                }
+                let version = fields.u16()?;
+                // Peers on other versions may lay out the rest differently,
+                // so stop here and let the handshake turn them away
+                if version != PROTOCOL_VERSION {
+                    return Ok(Message::Hello {
+                        version,
+                        role: Role::Player,
+                    });
+                }
+                let role = match fields.u8()? {
+                    0 => Role::Player,
+                    1 => Role::Spectator,
+                    _ => return Err(invalid("unknown role")),
+                };
+                Message::Hello { version, role }
+            }
+            KIND_WELCOME => Message::Welcome {
+                player: fields.u8()?,
+                seed: fields.u64()?,
+                settings: fields.settings()?,
+            },
+            KIND_REJECT => Message::Reject {
+                reason: String::from_utf8_lossy(fields.take(fields.0.len())?).into_owned(),
+            },
+            KIND_TURN => Message::Turn {
+                direction: fields.direction()?,
+            },
+            KIND_TICK => {
+                let tick = fields.u64()?;
+                let count = fields.u8()?;
+                let turns = (0..count)
+                    .map(|_| Ok((fields.u8()?, fields.direction()?)))
+                    .collect::<io::Result<_>>()?;
+                Message::Tick { tick, turns }
+            }
+            KIND_SNAPSHOT => Message::Snapshot {
+                round: Box::new(fields.snapshot()?),
+            },
+            kind => return Err(invalid(&format!("unknown message kind {}", kind))),
+        };
+        if !fields.0.is_empty() {
+            return Err(invalid("trailing bytes after message"));
+        }
+
+        Ok(message)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn direction_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    }
+}
+
+fn write_settings(payload: &mut Vec<u8>, settings: &GameSettings) {
+    payload.push(match settings.boundary {
+        BoundaryMode::Wrap => 0,
+        BoundaryMode::Walls => 1,
+    });
+    payload.extend_from_slice(&settings.width.to_be_bytes());
+    payload.extend_from_slice(&settings.height.to_be_bytes());
+    payload.push(match settings.difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Normal => 1,
+        Difficulty::Hard => 2,
+        Difficulty::Insane => 3,
+        Difficulty::Custom => 4,
+    });
+    let speed = settings.speed;
+    for ms in [speed.initial_ms, speed.step_ms, speed.minimum_ms] {
+        payload.extend_from_slice(&ms.to_be_bytes());
+    }
+}
+
+fn write_position(payload: &mut Vec<u8>, position: Position) {
+    // Boards are at most MAX_GRID_SIZE cells across, well within a u16
+    payload.extend_from_slice(&(position.x as u16).to_be_bytes());
+    payload.extend_from_slice(&(position.y as u16).to_be_bytes());
+}
+
+fn write_snapshot(payload: &mut Vec<u8>, round: &VersusSnapshot) {
+    write_settings(payload, &round.settings);
+    payload.extend_from_slice(&round.seed.to_be_bytes());
+    payload.extend_from_slice(&round.rng_word_pos.to_be_bytes());
+    payload.extend_from_slice(&round.tick.to_be_bytes());
+    write_position(payload, round.food);
+    payload.extend_from_slice(&(round.free.len() as u32).to_be_bytes());
+    for &position in &round.free {
+        write_position(payload, position);
+    }
+    payload.push(match round.result {
+        None => 0,
+        Some(RoundResult::Draw) => 1,
+        Some(RoundResult::Winner(player)) => 2 + player as u8,
+    });
+
+    for player in &round.players {
+        payload.push(direction_byte(player.direction));
+        payload.push(u8::from(player.is_alive));
+        payload.extend_from_slice(&player.score.to_be_bytes());
+        payload.extend_from_slice(&player.food_eaten.to_be_bytes());
+        for turns in player.turns {
+            payload.extend_from_slice(&turns.to_be_bytes());
+        }
+        let nanos = u64::try_from(player.time_played.as_nanos()).unwrap_or(u64::MAX);
+        payload.extend_from_slice(&nanos.to_be_bytes());
+        payload.push(player.queued.len() as u8);
+        payload.extend(
+            player
+                .queued
+                .iter()
+                .map(|direction| direction_byte(*direction)),
+        );
+        payload.extend_from_slice(&(player.snake.len() as u32).to_be_bytes());
+        for segment in &player.snake {
+            write_position(payload, *segment);
+        }
+    }
+}
+
+/// The unread rest of a frame's payload.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid("message cut short"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u128(&mut self) -> io::Result<u128> {
+        Ok(u128::from_be_bytes(self.take(16)?.try_into().unwrap()))
+    }
+
+    fn position(&mut self) -> io::Result<Position> {
+        let x = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        let y = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        Ok(Position {
+            x: u32::from(x),
+            y: u32::from(y),
+        })
+    }
+
+    /// A u32 count followed by that many positions.
+    fn positions(&mut self) -> io::Result<Vec<Position>> {
+        let len = self.u32()? as usize;
+        // Checked up front so a bogus length can't ask for a huge Vec
+        if len > self.0.len() / 4 {
+            return Err(invalid("message cut short"));
+        }
+        (0..len).map(|_| self.position()).collect()
+    }
+
+    fn direction(&mut self) -> io::Result<Direction> {
+        Direction::ALL
+            .get(self.u8()? as usize)
+            .copied()
+            .ok_or_else(|| invalid("unknown direction"))
+    }
+
+    /// Settings, checked with [`GameSettings::validate`] so a peer can't
+    /// make the other side build an impossible game.
+    fn settings(&mut self) -> io::Result<GameSettings> {
+        let boundary = *BoundaryMode::ALL
+            .get(self.u8()? as usize)
+            .ok_or_else(|| invalid("unknown boundary mode"))?;
+        let width = self.u32()?;
+        let height = self.u32()?;
+        let difficulty = match self.u8()? {
+            4 => Difficulty::Custom,
+            i => *Difficulty::PRESETS
+                .get(i as usize)
+                .ok_or_else(|| invalid("unknown difficulty"))?,
+        };
+        let speed = SpeedCurve {
+            initial_ms: self.u32()?,
+            step_ms: self.u32()?,
+            minimum_ms: self.u32()?,
+        };
+
+        let settings = GameSettings {
+            boundary,
+            width,
+            height,
+            difficulty,
+            speed,
+        };
+        settings.validate().map_err(|e| invalid(&e))?;
+        Ok(settings)
+    }
+
+    fn snapshot(&mut self) -> io::Result<VersusSnapshot> {
+        let settings = self.settings()?;
+        let seed = self.u64()?;
+        let rng_word_pos = self.u128()?;
+        let tick = self.u64()?;
+        let food = self.position()?;
+        let free = self.positions()?;
+        let result = match self.u8()? {
+            0 => None,
+            1 => Some(RoundResult::Draw),
+            byte if usize::from(byte - 2) < VERSUS_PLAYERS => {
+                Some(RoundResult::Winner(usize::from(byte - 2)))
+            }
+            _ => return Err(invalid("unknown round result")),
+        };
+
+        let mut players = Vec::with_capacity(VERSUS_PLAYERS);
+        for _ in 0..VERSUS_PLAYERS {
+            let direction = self.direction()?;
+            let is_alive = self.u8()? != 0;
+            let score = self.u32()?;
+            let food_eaten = self.u32()?;
+            let turns = [self.u32()?, self.u32()?, self.u32()?, self.u32()?];
+            let time_played = Duration::from_nanos(self.u64()?);
+            let queued = (0..self.u8()?)
+                .map(|_| self.direction())
+                .collect::<io::Result<_>>()?;
+            let snake = self.positions()?;
+            players.push(PlayerSnapshot {
+                snake,
+                direction,
+                queued,
+                is_alive,
+                score,
+                food_eaten,
+                turns,
+                time_played,
+            });
+        }
+
+        Ok(VersusSnapshot {
+            settings,
+            seed,
+            rng_word_pos,
+            tick,
+            food,
+            free,
+            result,
+            players: players.try_into().expect("one per player"),
+        })
+    }
+}
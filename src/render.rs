@@ -0,0 +1,119 @@
+use image::{Rgba, RgbaImage};
+
+use crate::game::{BoundaryMode, Game, GameSettings, Position};
+
+/// Outline of the board when its edges are walls.
+pub const WALL_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// Drawn over the board when a game is lost or won, or a versus round ends.
+pub const GAME_OVER_TINT: [f32; 4] = [1.0, 0.0, 0.0, 0.5];
+pub const VICTORY_TINT: [f32; 4] = [1.0, 1.0, 0.0, 0.4];
+pub const ROUND_OVER_TINT: [f32; 4] = [1.0, 1.0, 1.0, 0.2];
+
+/// Opacity of a snake that crashed in a versus round.
+pub const CRASHED_ALPHA: f32 = 0.4;
+
+/// Most pixels [`render_game`] draws in one image, 8192 by 8192 or 256 MiB
+/// of RGBA.
+pub const MAX_RENDER_PIXELS: u64 = 8192 * 8192;
+
+/// Cell size and colours for [`render_game`], as RGBA from 0 to 1. The
+/// defaults match the window's.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderStyle {
+    /// Side of a cell in pixels
+    pub cell_size: u32,
+    pub background: [f32; 4],
+    pub snake: [f32; 4],
+    pub food: [f32; 4],
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        Self {
+            cell_size: 25,
+            background: [0.0, 0.0, 0.0, 1.0],
+            snake: [0.0, 1.0, 0.0, 1.0],
+            food: [1.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl RenderStyle {
+    /// Width and height in pixels of a board with `settings`, the board size
+    /// times the cell size. An error if that is more than
+    /// [`MAX_RENDER_PIXELS`].
+    pub fn image_size(&self, settings: &GameSettings) -> Result<(u32, u32), String> {
+        let width = u64::from(settings.width) * u64::from(self.cell_size);
+        let height = u64::from(settings.height) * u64::from(self.cell_size);
+        if width * height > MAX_RENDER_PIXELS {
+            return Err(format!(
+                "{}x{} cells at {} pixels each make a {}x{} image, over the limit of {} pixels",
+                settings.width, settings.height, self.cell_size, width, height, MAX_RENDER_PIXELS
+            ));
+        }
+        Ok((width as u32, height as u32))
+    }
+}
+
+/// Draws the board of `game` the way the window does, without the HUD:
+/// the snake, the food, the walls and the game-over or victory tint. The
+/// image is [`RenderStyle::image_size`].
+///
+/// # Panics
+///
+/// If the image would be more than [`MAX_RENDER_PIXELS`].
+pub fn render_game(game: &Game, style: &RenderStyle) -> RgbaImage {
+    let settings = game.settings();
+    let (width, height) = style
+        .image_size(settings)
+        .unwrap_or_else(|e| panic!("board too large to render: {}", e));
+    let mut image = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+    fill(&mut image, [0, 0, width, height], style.background);
+
+    for segment in game.snake() {
+        fill_cell(&mut image, *segment, style.snake, style.cell_size);
+    }
+    if !game.is_won() {
+        fill_cell(&mut image, game.food(), style.food, style.cell_size);
+    }
+
+    // Two pixels wide, like the window's outline
+    if settings.boundary == BoundaryMode::Walls {
+        let edge = 2.min(width).min(height);
+        fill(&mut image, [0, 0, width, edge], WALL_COLOR);
+        fill(&mut image, [0, height - edge, width, edge], WALL_COLOR);
+        fill(&mut image, [0, 0, edge, height], WALL_COLOR);
+        fill(&mut image, [width - edge, 0, edge, height], WALL_COLOR);
+    }
+
+    if game.is_game_over() {
+        fill(&mut image, [0, 0, width, height], GAME_OVER_TINT);
+    }
+    if game.is_won() {
+        fill(&mut image, [0, 0, width, height], VICTORY_TINT);
+    }
+    image
+}
+
+fn fill_cell(image: &mut RgbaImage, position: Position, color: [f32; 4], cell_size: u32) {
+    let [x, y] = [position.x * cell_size, position.y * cell_size];
+    fill(image, [x, y, cell_size, cell_size], color);
+}
+
+/// Blends `color` over the `[x, y, width, height]` rectangle, clipped to
+/// the image.
+fn fill(image: &mut RgbaImage, [x, y, width, height]: [u32; 4], color: [f32; 4]) {
+    let [r, g, b, a] = color.map(|channel| channel.clamp(0.0, 1.0));
+    let right = x.saturating_add(width).min(image.width());
+    let bottom = y.saturating_add(height).min(image.height());
+    for py in y..bottom {
+        for px in x..right {
+            let Rgba(pixel) = image.get_pixel_mut(px, py);
+            for (channel, over) in pixel.iter_mut().zip([r, g, b]) {
+                let under = f32::from(*channel) / 255.0;
+                *channel = ((under * (1.0 - a) + over * a) * 255.0).round() as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::game::{Direction, Game, GameSettings, Timestep, MAX_CATCH_UP_TICKS};
+
+/// Bumped whenever a change to the file layout or to the simulation would
+/// make older replays play out differently.
+pub const REPLAY_VERSION: u32 = 3;
+
+/// File extension used for saved replays.
+pub const REPLAY_EXTENSION: &str = "replay";
+
+/// A turn passed to [`Game::change_direction`] while the game was at `tick`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub tick: u64,
+    pub direction: Direction,
+}
+
+/// A whole game described by its seed, settings and inputs. Feeding the
+/// inputs back into a game built from the same seed reproduces it exactly.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub settings: GameSettings,
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    pub fn new(settings: GameSettings, seed: u64, inputs: Vec<ReplayInput>) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            settings,
+            inputs,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let replay: Replay = serde_json::from_reader(reader)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "replay version {} is not supported (expected {})",
+                    replay.version, REPLAY_VERSION
+                ),
+            ));
+        }
+        replay
+            .settings
+            .validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.save_with(
+            path,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )
+    }
+
+    /// Like [`Replay::save`], but fails with [`io::ErrorKind::AlreadyExists`]
+    /// instead of overwriting a file that is already there.
+    pub fn save_new(&self, path: &Path) -> io::Result<()> {
+        self.save_with(path, OpenOptions::new().write(true).create_new(true))
+    }
+
+    fn save_with(&self, path: &Path, options: &OpenOptions) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut writer = BufWriter::new(options.open(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+}
+
+/// Plays a [`Replay`] back on its own clock, which can be paused, sped up,
+/// slowed down or advanced one tick at a time.
+pub struct ReplayPlayer {
+    replay: Replay,
+    game: Game,
+    next_input: usize,
+    timestep: Timestep,
+    pub rate: f64,
+    pub paused: bool,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let game = Game::with_settings(replay.settings, replay.seed);
+        Self {
+            replay,
+            game,
+            next_input: 0,
+            timestep: Timestep::default(),
+            rate: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.game.is_finished()
+    }
+
+    /// Starts the replay over from the first tick.
+    pub fn restart(&mut self) {
+        self.game = Game::with_settings(self.replay.settings, self.replay.seed);
+        self.next_input = 0;
+        self.timestep = Timestep::default();
+    }
+
+    /// Advances playback by `dt` seconds of wall-clock time scaled by
+    /// `rate`. Returns `true` when the board changed.
+    pub fn update(&mut self, dt: f64) -> bool {
+        if self.paused || self.is_finished() {
+            return false;
+        }
+
+        self.timestep.advance(dt * self.rate);
+        let mut ticked = false;
+        // Scale the catch-up cap with the rate so fast playback is not capped
+        let max_ticks = (MAX_CATCH_UP_TICKS as f64 * self.rate.max(1.0)).ceil() as u32;
+        while !self.is_finished() && self.timestep.take_tick(self.game.speed(), max_ticks) {
+            ticked |= self.step();
+        }
+
+        ticked
+    }
+
+    /// Feeds the inputs recorded for the current tick and runs exactly one
+    /// tick.
+    pub fn step(&mut self) -> bool {
+        while let Some(input) = self.replay.inputs.get(self.next_input) {
+            if input.tick > self.game.tick() {
+                break;
+            }
+            self.game.change_direction(input.direction);
+            self.next_input += 1;
+        }
+
+        self.game.step()
+    }
+}
@@ -0,0 +1,106 @@
+use piston_window::*;
+use rust_snake_game::{Game, ReplayPlayer};
+use std::error::Error;
+
+use crate::board::{self, Layout};
+use crate::config::Config;
+use crate::{hud, new_events, open_window};
+
+/// Playback speeds stepped through with Up and Down.
+const RATES: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const NORMAL_RATE: usize = 2;
+
+/// Which keys do what while watching a replay.
+pub const HELP: &str = "Space pause  Up/Down speed  Right step  R restart";
+
+/// A replay being watched. Space pauses, Up and Down change the speed,
+/// Right steps one tick while paused and R starts over.
+pub struct ReplayView {
+    player: ReplayPlayer,
+    /// Index into [`RATES`]
+    rate: usize,
+}
+
+impl ReplayView {
+    pub fn new(player: ReplayPlayer) -> Self {
+        Self {
+            player,
+            rate: NORMAL_RATE,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        self.player.game()
+    }
+
+    pub fn on_key(&mut self, key: Key) {
+        let player = &mut self.player;
+        match key {
+            Key::Space => player.paused = !player.paused,
+            Key::Up => self.rate = (self.rate + 1).min(RATES.len() - 1),
+            Key::Down => self.rate = self.rate.saturating_sub(1),
+            Key::Right | Key::Period => {
+                player.paused = true;
+                player.step();
+            }
+            Key::R => player.restart(),
+            _ => {}
+        }
+        player.rate = RATES[self.rate];
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.player.update(dt);
+    }
+
+    /// Playback speed, whether it is running and the tick it is on.
+    pub fn status(&self) -> String {
+        let state = if self.player.is_finished() {
+            "finished"
+        } else if self.player.paused {
+            "paused"
+        } else {
+            "playing"
+        };
+        format!(
+            "Replay x{} {} - tick {}",
+            self.player.rate,
+            state,
+            self.game().tick()
+        )
+    }
+}
+
+/// Plays a replay in a window, drawn with the configured cell size and
+/// colours.
+pub fn run(mut view: ReplayView, config: &Config) -> Result<(), Box<dyn Error>> {
+    let layout = Layout::new(view.game().settings(), &config.display);
+    let [window_width, window_height] = layout.window_size();
+    let mut window = open_window("Snake Game (replay)", &layout, true)?;
+    let mut glyphs = hud::load_font(&mut window);
+    let mut events = new_events();
+
+    while let Some(e) = events.next(&mut window) {
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            view.on_key(key);
+        }
+
+        if let Some(update_args) = e.update_args() {
+            view.update(update_args.dt);
+        }
+
+        window.draw_2d(&e, |c, g, device| {
+            clear(config.display.background.0, g);
+            board::draw(view.game(), &layout, &config.display, &c, g);
+
+            if let Some(glyphs) = glyphs.as_mut() {
+                let (width, height) = (window_width as f64, window_height as f64);
+                hud::draw(view.game(), None, glyphs, width, &c, g);
+                hud::draw_centered(&view.status(), height - 30.0, width, glyphs, &c, g);
+                hud::draw_centered(HELP, height - 12.0, width, glyphs, &c, g);
+                glyphs.factory.encoder.flush(device);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,567 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::game::{BoundaryMode, Difficulty, Direction, GameSettings, DEFAULT_GRID_SIZE};
+
+/// File every game is appended to in CSV mode.
+pub const CSV_FILE_NAME: &str = "snake_game_stats.csv";
+
+/// How [`GameStats::save`] writes a finished game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsFormat {
+    /// One human-readable `.txt` report per game.
+    #[default]
+    Text,
+    /// One `.json` document per game.
+    Json,
+    /// One line per game appended to a shared `.csv` file.
+    Csv,
+}
+
+impl StatsFormat {
+    pub const ALL: [StatsFormat; 3] = [StatsFormat::Text, StatsFormat::Json, StatsFormat::Csv];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatsFormat::Text => "text",
+            StatsFormat::Json => "json",
+            StatsFormat::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for StatsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(StatsFormat::Text),
+            "json" => Ok(StatsFormat::Json),
+            "csv" => Ok(StatsFormat::Csv),
+            _ => Err(format!(
+                "unknown stats format `{}` (expected text, json or csv)",
+                s
+            )),
+        }
+    }
+}
+
+/// How a versus round ended for one of its players.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersusOutcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl VersusOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersusOutcome::Win => "win",
+            VersusOutcome::Loss => "loss",
+            VersusOutcome::Draw => "draw",
+        }
+    }
+}
+
+impl fmt::Display for VersusOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub struct GameStats {
+    pub start_time: SystemTime,
+    pub time_played: Duration,
+    pub up_turns: u32,
+    pub down_turns: u32,
+    pub left_turns: u32,
+    pub right_turns: u32,
+    pub food_eaten: u32,
+    pub timestamp: u64,
+    pub seed: u64,
+    pub settings: GameSettings,
+    pub board_cleared: bool,
+    /// Which snake these are for in a versus round, from 0; `None` for a
+    /// single-player game
+    pub player: Option<usize>,
+    /// How the versus round ended for this player, once it has
+    pub versus_result: Option<VersusOutcome>,
+}
+
+/// Flat snapshot of a finished game, shared by the JSON and CSV exports.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatsRecord {
+    pub started_at: String,
+    pub timestamp: u64,
+    pub seed: u64,
+    pub mode: BoundaryMode,
+    /// Missing from records saved before difficulties existed, which were
+    /// all played at normal speed
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Missing from records saved before the board size could be set
+    #[serde(default = "default_grid_size")]
+    pub width: u32,
+    #[serde(default = "default_grid_size")]
+    pub height: u32,
+    pub time_played_secs: f64,
+    pub final_score: u32,
+    pub final_length: usize,
+    /// Missing from records saved before a board could be cleared
+    #[serde(default)]
+    pub board_cleared: bool,
+    pub food_eaten: u32,
+    pub up_turns: u32,
+    pub down_turns: u32,
+    pub left_turns: u32,
+    pub right_turns: u32,
+    pub total_turns: u32,
+    /// Which snake this was in a versus round, from 1; empty for a
+    /// single-player game and in records saved before versus rounds
+    #[serde(default)]
+    pub player: Option<usize>,
+    /// How the versus round ended for [`StatsRecord::player`]
+    #[serde(default)]
+    pub versus_result: Option<VersusOutcome>,
+}
+
+impl StatsRecord {
+    const CSV_HEADER: &'static str = "started_at,timestamp,seed,mode,difficulty,width,height,time_played_secs,\
+        final_score,final_length,board_cleared,food_eaten,up_turns,down_turns,left_turns,right_turns,total_turns,\
+        player,versus_result";
+
+    fn to_csv_line(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{:.3},{},{},{},{},{},{},{},{},{},{},{}",
+            self.started_at,
+            self.timestamp,
+            self.seed,
+            self.mode,
+            self.difficulty,
+            self.width,
+            self.height,
+            self.time_played_secs,
+            self.final_score,
+            self.final_length,
+            self.board_cleared,
+            self.food_eaten,
+            self.up_turns,
+            self.down_turns,
+            self.left_turns,
+            self.right_turns,
+            self.total_turns,
+            self.player
+                .map_or(String::new(), |player| player.to_string()),
+            self.versus_result
+                .map_or(String::new(), |result| result.to_string())
+        )
+    }
+
+    /// Whether this is one player's side of a versus round.
+    pub fn is_versus(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// The record as a CSV line with `columns` in that order, so it lines up
+    /// with a file started by an older version. Columns the record doesn't
+    /// know are left empty, and its own columns the file lacks are left out.
+    fn to_csv_line_for(&self, columns: &[&str]) -> String {
+        let line = self.to_csv_line();
+        let values: Vec<(&str, &str)> = Self::CSV_HEADER.split(',').zip(line.split(',')).collect();
+        columns
+            .iter()
+            .map(|column| {
+                values
+                    .iter()
+                    .find(|(name, _)| name == column)
+                    .map_or("", |(_, value)| value)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+fn default_grid_size() -> u32 {
+    DEFAULT_GRID_SIZE
+}
+
+/// Games read back from a stats directory.
+#[derive(Debug, Default)]
+pub struct StatsHistory {
+    /// Oldest first
+    pub records: Vec<StatsRecord>,
+    /// Files and CSV rows that could not be read back, which includes every
+    /// text report
+    pub skipped: usize,
+}
+
+impl StatsHistory {
+    /// Reads every JSON file and the CSV file in `dir`. A missing directory
+    /// is an empty history.
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let mut history = Self::default();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(history),
+            Err(e) => return Err(e),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => match serde_json::from_str(&fs::read_to_string(&path)?) {
+                    Ok(record) => history.records.push(record),
+                    Err(_) => history.skipped += 1,
+                },
+                Some("csv") if path.file_name() == Some(CSV_FILE_NAME.as_ref()) => {
+                    history.read_csv(&fs::read_to_string(&path)?)
+                }
+                Some("txt") => history.skipped += 1,
+                _ => {}
+            }
+        }
+
+        history.records.sort_by_key(|record| record.timestamp);
+        Ok(history)
+    }
+
+    fn read_csv(&mut self, contents: &str) {
+        let mut lines = contents.lines();
+        let Some(header) = lines.next() else {
+            return;
+        };
+        let columns: Vec<&str> = header.split(',').collect();
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let values: Vec<&str> = line.split(',').collect();
+            if values.len() != columns.len() {
+                self.skipped += 1;
+                continue;
+            }
+
+            // Go through JSON so the fields are matched up by name. Empty
+            // values are left out, so they take the field's default.
+            let row: serde_json::Map<String, serde_json::Value> = columns
+                .iter()
+                .zip(values)
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(column, value)| (column.to_string(), csv_value(value)))
+                .collect();
+            match serde_json::from_value(row.into()) {
+                Ok(record) => self.records.push(record),
+                Err(_) => self.skipped += 1,
+            }
+        }
+    }
+
+    /// Totals over the single-player games, with versus rounds counted on
+    /// their own so they don't skew the scores.
+    pub fn summary(&self) -> StatsSummary {
+        let mut summary = StatsSummary::default();
+        let mut total_score = 0u64;
+        for record in &self.records {
+            if record.is_versus() {
+                // Both players' records describe the same round, so it is
+                // counted from the first player's side only
+                if record.player == Some(1) {
+                    summary.versus_rounds += 1;
+                    match record.versus_result {
+                        Some(VersusOutcome::Win) => summary.versus_wins[0] += 1,
+                        Some(VersusOutcome::Loss) => summary.versus_wins[1] += 1,
+                        Some(VersusOutcome::Draw) | None => summary.versus_draws += 1,
+                    }
+                }
+                continue;
+            }
+            summary.games += 1;
+            summary.boards_cleared += usize::from(record.board_cleared);
+            summary.time_played_secs += record.time_played_secs;
+            summary.best_score = summary.best_score.max(record.final_score);
+            summary.food_eaten += u64::from(record.food_eaten);
+            summary.total_turns += u64::from(record.total_turns);
+            total_score += u64::from(record.final_score);
+        }
+        if summary.games > 0 {
+            summary.average_score = total_score as f64 / summary.games as f64;
+        }
+        summary
+    }
+}
+
+fn csv_value(value: &str) -> serde_json::Value {
+    if let Ok(n) = value.parse::<u64>() {
+        n.into()
+    } else if let Ok(x) = value.parse::<f64>() {
+        x.into()
+    } else if let Ok(b) = value.parse::<bool>() {
+        b.into()
+    } else {
+        value.into()
+    }
+}
+
+/// Totals over a [`StatsHistory`]. Everything but the `versus_` fields
+/// covers single-player games only.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StatsSummary {
+    pub games: usize,
+    pub boards_cleared: usize,
+    pub time_played_secs: f64,
+    pub best_score: u32,
+    pub average_score: f64,
+    pub food_eaten: u64,
+    pub total_turns: u64,
+    pub versus_rounds: usize,
+    /// Rounds won by the first and the second player
+    pub versus_wins: [usize; 2],
+    pub versus_draws: usize,
+}
+
+impl fmt::Display for StatsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let played = self.time_played_secs as u64;
+        writeln!(f, "Games played: {}", self.games)?;
+        writeln!(f, "Boards cleared: {}", self.boards_cleared)?;
+        writeln!(
+            f,
+            "Time played: {}h {}m {}s",
+            played / 3600,
+            played / 60 % 60,
+            played % 60
+        )?;
+        writeln!(f, "Best score: {}", self.best_score)?;
+        writeln!(f, "Average score: {:.1}", self.average_score)?;
+        writeln!(f, "Food eaten: {}", self.food_eaten)?;
+        write!(f, "Total turns: {}", self.total_turns)?;
+        if self.versus_rounds > 0 {
+            write!(
+                f,
+                "\nVersus rounds: {} (player 1 won {}, player 2 won {}, {} drawn)",
+                self.versus_rounds, self.versus_wins[0], self.versus_wins[1], self.versus_draws
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl GameStats {
+    pub fn new(seed: u64, settings: GameSettings) -> Self {
+        let now = SystemTime::now();
+        let timestamp = now
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+
+        Self {
+            start_time: now,
+            time_played: Duration::from_secs(0),
+            up_turns: 0,
+            down_turns: 0,
+            left_turns: 0,
+            right_turns: 0,
+            food_eaten: 0,
+            timestamp,
+            seed,
+            settings,
+            board_cleared: false,
+            player: None,
+            versus_result: None,
+        }
+    }
+
+    /// Counts `dt` seconds towards the time played. Only time the game
+    /// actually ran for is added, so pauses are left out.
+    pub fn add_play_time(&mut self, dt: f64) {
+        self.time_played += Duration::from_secs_f64(dt.max(0.0));
+    }
+
+    /// Adds a turn in `direction` to the movement statistics.
+    pub fn count_turn(&mut self, direction: Direction) {
+        match direction {
+            Direction::Up => self.up_turns += 1,
+            Direction::Down => self.down_turns += 1,
+            Direction::Left => self.left_turns += 1,
+            Direction::Right => self.right_turns += 1,
+        }
+    }
+
+    pub fn total_turns(&self) -> u32 {
+        self.up_turns + self.down_turns + self.left_turns + self.right_turns
+    }
+
+    pub fn record(&self, final_score: u32, final_length: usize) -> StatsRecord {
+        let dt: DateTime<Local> = self.start_time.into();
+
+        StatsRecord {
+            started_at: dt.to_rfc3339_opts(SecondsFormat::Secs, false),
+            timestamp: self.timestamp,
+            seed: self.seed,
+            mode: self.settings.boundary,
+            difficulty: self.settings.difficulty,
+            width: self.settings.width,
+            height: self.settings.height,
+            time_played_secs: self.time_played.as_secs_f64(),
+            final_score,
+            final_length,
+            board_cleared: self.board_cleared,
+            food_eaten: self.food_eaten,
+            up_turns: self.up_turns,
+            down_turns: self.down_turns,
+            left_turns: self.left_turns,
+            right_turns: self.right_turns,
+            total_turns: self.total_turns(),
+            player: self.player.map(|player| player + 1),
+            versus_result: self.versus_result,
+        }
+    }
+
+    /// Writes the game out in `format` into `dir`, creating the directory if
+    /// needed, and returns the file written to.
+    pub fn save(
+        &self,
+        dir: &Path,
+        format: StatsFormat,
+        final_score: u32,
+        final_length: usize,
+    ) -> std::io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+
+        match format {
+            StatsFormat::Text => self.save_to_file(dir, final_score),
+            StatsFormat::Json => self.save_json(dir, final_score, final_length),
+            StatsFormat::Csv => self.append_csv(dir, final_score, final_length),
+        }
+    }
+
+    /// Creates this game's own file, e.g.
+    /// `20250301_142501_snake_game_stats.txt`, or
+    /// `20250301_142501_snake_game_stats_player2.txt` for the second snake in
+    /// a versus round. A game that started in the same second as one already
+    /// saved gets a numbered name (`_2`, `_3`, ...) rather than overwriting it.
+    fn create_file(&self, dir: &Path, extension: &str) -> io::Result<(PathBuf, File)> {
+        // Format timestamp as human-readable date/time for filename
+        let dt: DateTime<Local> = self.start_time.into();
+        let player = self
+            .player
+            .map_or(String::new(), |player| format!("_player{}", player + 1));
+        let stem = format!("{}_snake_game_stats{}", dt.format("%Y%m%d_%H%M%S"), player);
+        let mut number = 1;
+        loop {
+            let name = match number {
+                1 => format!("{}.{}", stem, extension),
+                _ => format!("{}_{}.{}", stem, number, extension),
+            };
+            let path = dir.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => number += 1,
+                result => return result.map(|file| (path, file)),
+            }
+        }
+    }
+
+    pub fn save_to_file(&self, dir: &Path, final_score: u32) -> std::io::Result<PathBuf> {
+        let dt: DateTime<Local> = self.start_time.into();
+        let (path, mut file) = self.create_file(dir, "txt")?;
+
+        // Convert times to more readable format
+        let time_played_secs = self.time_played.as_secs();
+        let minutes = time_played_secs / 60;
+        let seconds = time_played_secs % 60;
+
+        // Write stats to file
+        writeln!(file, "Snake Game Statistics")?;
+        writeln!(file, "=====================")?;
+        writeln!(file, "Game started at: {}", dt.format("%Y-%m-%d %H:%M:%S"))?;
+        writeln!(file, "Seed: {}", self.seed)?;
+        if let Some(player) = self.player {
+            writeln!(file, "Versus player: {}", player + 1)?;
+        }
+        if let Some(result) = self.versus_result {
+            writeln!(file, "Versus result: {}", result)?;
+        }
+        writeln!(file, "Mode: {}", self.settings.boundary)?;
+        writeln!(file, "Difficulty: {}", self.settings.difficulty)?;
+        writeln!(
+            file,
+            "Board: {}x{}",
+            self.settings.width, self.settings.height
+        )?;
+        writeln!(file, "Time played: {}m {}s", minutes, seconds)?;
+        let result = if self.board_cleared {
+            "board cleared"
+        } else {
+            "game over"
+        };
+        writeln!(file, "Result: {}", result)?;
+        writeln!(file, "Final score: {}", final_score)?;
+        writeln!(file, "Food eaten: {}", self.food_eaten)?;
+        writeln!(file)?;
+        writeln!(file, "Movement Statistics:")?;
+        writeln!(file, "  Up turns: {}", self.up_turns)?;
+        writeln!(file, "  Down turns: {}", self.down_turns)?;
+        writeln!(file, "  Left turns: {}", self.left_turns)?;
+        writeln!(file, "  Right turns: {}", self.right_turns)?;
+        writeln!(file)?;
+        writeln!(file, "Total turns: {}", self.total_turns())?;
+
+        Ok(path)
+    }
+
+    fn save_json(
+        &self,
+        dir: &Path,
+        final_score: u32,
+        final_length: usize,
+    ) -> std::io::Result<PathBuf> {
+        let (path, mut file) = self.create_file(dir, "json")?;
+        serde_json::to_writer_pretty(&mut file, &self.record(final_score, final_length))?;
+        writeln!(file)?;
+
+        Ok(path)
+    }
+
+    fn append_csv(
+        &self,
+        dir: &Path,
+        final_score: u32,
+        final_length: usize,
+    ) -> std::io::Result<PathBuf> {
+        let path = dir.join(CSV_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+        let mut header = String::new();
+        BufReader::new(&file).read_line(&mut header)?;
+
+        let record = self.record(final_score, final_length);
+        // A fresh file gets a header row first; one started by an older
+        // version keeps its own, so rows still match the header they sit under
+        let line = if header.trim_end().is_empty() {
+            writeln!(file, "{}", StatsRecord::CSV_HEADER)?;
+            record.to_csv_line()
+        } else {
+            let columns: Vec<&str> = header.trim_end().split(',').collect();
+            record.to_csv_line_for(&columns)
+        };
+        writeln!(file, "{}", line)?;
+
+        Ok(path)
+    }
+}
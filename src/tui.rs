@@ -0,0 +1,546 @@
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableFocusChange, EnableFocusChange, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers,
+};
+use crossterm::style::{
+    Color as TermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use piston_window::Key;
+use rust_snake_game::{
+    BoundaryMode, Game, GameSettings, Position, Versus, CRASHED_ALPHA, GAME_OVER_TINT,
+    ROUND_OVER_TINT, VICTORY_TINT, WALL_COLOR,
+};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::app::App;
+use crate::config::{Config, DisplayConfig};
+use crate::hud::{self, Menu, BACKGROUND_COLOR, PANEL_COLOR, TEXT_COLOR};
+use crate::net_view::NetRound;
+use crate::replay_view::{self, ReplayView};
+use crate::FRAMES_PER_SECOND;
+
+/// Each board cell is two characters wide, which comes out roughly square in
+/// most terminal fonts.
+const CELL_WIDTH: u16 = 2;
+const CELL: char = '█';
+
+/// Edge of the board: solid when it is a wall, shaded when snakes wrap.
+const WALL: char = '█';
+const WRAP_EDGE: char = '░';
+
+/// Rows above the board for the HUD.
+const HUD_ROWS: u16 = 2;
+
+/// Plays from the title screen in the terminal.
+pub fn play(mut app: App) -> io::Result<()> {
+    let display = app.config().display.clone();
+    run(&mut app, &display)
+}
+
+/// Plays a replay in the terminal, with the same keys as in a window.
+pub fn replay(mut view: ReplayView, display: &DisplayConfig) -> io::Result<()> {
+    run(&mut view, display)
+}
+
+/// Hosts, joins or watches a networked round in the terminal.
+pub fn net(round: NetRound, config: &Config) -> io::Result<()> {
+    run(&mut NetView { round, config }, &config.display)
+}
+
+/// Something the terminal can show and pass keys to.
+trait View {
+    fn on_key(&mut self, key: Key, text: Option<char>);
+    fn update(&mut self, dt: f64);
+    fn frame(&self) -> Frame<'_>;
+
+    /// Whether Esc leaves, like a window opened with exit on Esc.
+    fn exits_on_esc(&self) -> bool {
+        true
+    }
+
+    fn is_done(&self) -> bool {
+        false
+    }
+
+    fn lose_focus(&mut self) {}
+}
+
+/// Everything on screen at one moment.
+struct Frame<'a> {
+    settings: &'a GameSettings,
+    round: Round<'a>,
+    /// The two lines of the HUD strip
+    hud: Option<[String; 2]>,
+    menu: Option<Menu>,
+    /// Lines under the board
+    status: Vec<String>,
+}
+
+enum Round<'a> {
+    Empty,
+    Game(&'a Game),
+    Versus(&'a Versus),
+}
+
+impl View for App {
+    fn on_key(&mut self, key: Key, text: Option<char>) {
+        if let Some(text) = text {
+            self.on_text(&text.to_string());
+        }
+        App::on_key(self, key);
+    }
+
+    fn update(&mut self, dt: f64) {
+        App::update(self, dt);
+    }
+
+    fn frame(&self) -> Frame<'_> {
+        let (settings, round, hud) = match self.versus() {
+            Some(versus) => (
+                versus.settings(),
+                Round::Versus(versus),
+                hud::versus_status_lines(versus),
+            ),
+            None => (
+                self.game().settings(),
+                Round::Game(self.game()),
+                hud::status_lines(self.game(), Some(self.best())),
+            ),
+        };
+        Frame {
+            settings,
+            round,
+            hud: Some(hud),
+            menu: self.menu(),
+            status: self.status().into_iter().collect(),
+        }
+    }
+
+    // The menus use Esc to go back, and quit from the title screen
+    fn exits_on_esc(&self) -> bool {
+        false
+    }
+
+    fn is_done(&self) -> bool {
+        self.is_quitting()
+    }
+
+    fn lose_focus(&mut self) {
+        App::lose_focus(self);
+    }
+}
+
+impl View for ReplayView {
+    fn on_key(&mut self, key: Key, _: Option<char>) {
+        ReplayView::on_key(self, key);
+    }
+
+    fn update(&mut self, dt: f64) {
+        ReplayView::update(self, dt);
+    }
+
+    fn frame(&self) -> Frame<'_> {
+        Frame {
+            settings: self.game().settings(),
+            round: Round::Game(self.game()),
+            hud: Some(hud::status_lines(self.game(), None)),
+            menu: None,
+            status: vec![self.status(), replay_view::HELP.to_string()],
+        }
+    }
+}
+
+/// A networked round with the key bindings to steer it.
+struct NetView<'a> {
+    round: NetRound,
+    config: &'a Config,
+}
+
+impl View for NetView<'_> {
+    fn on_key(&mut self, key: Key, _: Option<char>) {
+        self.round.on_key(key, self.config);
+    }
+
+    fn update(&mut self, dt: f64) {
+        self.round.update(dt);
+    }
+
+    fn frame(&self) -> Frame<'_> {
+        let round = &self.round;
+        let menu = round.menu();
+        Frame {
+            settings: round.settings(),
+            round: round.versus().map_or(Round::Empty, Round::Versus),
+            hud: round.versus().map(hud::versus_status_lines),
+            status: match menu {
+                Some(_) => Vec::new(),
+                None => vec![round.status()],
+            },
+            menu,
+        }
+    }
+}
+
+/// Puts the terminal in raw mode on the alternate screen, and back the way
+/// it was when dropped, panics included.
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let terminal = Terminal;
+        execute!(io::stdout(), EnterAlternateScreen, Hide, EnableFocusChange)?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            DisableFocusChange,
+            ResetColor,
+            Show,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run(view: &mut impl View, display: &DisplayConfig) -> io::Result<()> {
+    let _terminal = Terminal::enter()?;
+    let mut out = io::stdout();
+    let frame_time = Duration::from_secs(1) / FRAMES_PER_SECOND as u32;
+    let mut last_update = Instant::now();
+    let mut shown: Option<Canvas> = None;
+
+    loop {
+        // Take input until the next frame is due
+        let next_frame = last_update + frame_time;
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    let ctrl_c = key.modifiers.contains(KeyModifiers::CONTROL)
+                        && key.code == KeyCode::Char('c');
+                    if ctrl_c || (key.code == KeyCode::Esc && view.exits_on_esc()) {
+                        return Ok(());
+                    }
+                    let (key, text) = piston_key(key);
+                    view.on_key(key, text);
+                }
+                Event::FocusLost => view.lose_focus(),
+                // Start over on a clean screen
+                Event::Resize(..) => shown = None,
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        view.update((now - last_update).as_secs_f64());
+        last_update = now;
+        if view.is_done() {
+            return Ok(());
+        }
+
+        let (width, height) = terminal::size()?;
+        let canvas = render(&view.frame(), display, width, height);
+        if shown.as_ref() != Some(&canvas) {
+            canvas.show(&mut out, shown.as_ref())?;
+            shown = Some(canvas);
+        }
+    }
+}
+
+/// The key as the rest of the game knows it, so the configured bindings
+/// work the same as in a window, plus the character typed if any.
+fn piston_key(event: KeyEvent) -> (Key, Option<char>) {
+    match event.code {
+        KeyCode::Up => (Key::Up, None),
+        KeyCode::Down => (Key::Down, None),
+        KeyCode::Left => (Key::Left, None),
+        KeyCode::Right => (Key::Right, None),
+        KeyCode::Enter => (Key::Return, None),
+        KeyCode::Esc => (Key::Escape, None),
+        KeyCode::Backspace => (Key::Backspace, None),
+        KeyCode::Tab => (Key::Tab, None),
+        KeyCode::Delete => (Key::Delete, None),
+        // Piston numbers the printable keys by their lowercase ASCII code
+        KeyCode::Char(ch) if ch.is_ascii() => (Key::from(ch.to_ascii_lowercase() as u32), Some(ch)),
+        KeyCode::Char(ch) => (Key::Unknown, Some(ch)),
+        _ => (Key::Unknown, None),
+    }
+}
+
+type Rgb = [f32; 3];
+
+fn rgb([r, g, b, _]: [f32; 4]) -> Rgb {
+    [r, g, b]
+}
+
+/// `over` drawn on top of `under` with its alpha.
+fn blend(under: Rgb, [r, g, b, a]: [f32; 4]) -> Rgb {
+    let mix = |under: f32, over: f32| under * (1.0 - a) + over * a;
+    [mix(under[0], r), mix(under[1], g), mix(under[2], b)]
+}
+
+fn term_color([r, g, b]: Rgb) -> TermColor {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    TermColor::Rgb {
+        r: byte(r),
+        g: byte(g),
+        b: byte(b),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell {
+    ch: char,
+    fg: Rgb,
+    bg: Rgb,
+}
+
+/// The whole terminal as characters and colours, drawn into before any of it
+/// is written out.
+#[derive(Clone, Debug, PartialEq)]
+struct Canvas {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Canvas {
+    fn new(width: u16, height: u16, bg: Rgb) -> Self {
+        let blank = Cell {
+            ch: ' ',
+            fg: rgb(TEXT_COLOR),
+            bg,
+        };
+        Self {
+            width,
+            height,
+            cells: vec![blank; usize::from(width) * usize::from(height)],
+        }
+    }
+
+    fn cell(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        if x < self.width && y < self.height {
+            self.cells
+                .get_mut(usize::from(y) * usize::from(self.width) + usize::from(x))
+        } else {
+            None
+        }
+    }
+
+    fn put(&mut self, x: u16, y: u16, ch: char, fg: Rgb) {
+        if let Some(cell) = self.cell(x, y) {
+            cell.ch = ch;
+            cell.fg = fg;
+        }
+    }
+
+    /// Covers `width` by `height` cells from (`x`, `y`) in `color`.
+    fn fill(&mut self, x: u16, y: u16, width: u16, height: u16, color: [f32; 4]) {
+        for y in y..y.saturating_add(height) {
+            for x in x..x.saturating_add(width) {
+                if let Some(cell) = self.cell(x, y) {
+                    cell.fg = blend(cell.fg, color);
+                    cell.bg = blend(cell.bg, color);
+                }
+            }
+        }
+    }
+
+    /// Writes `text` over whatever is there, keeping the background.
+    fn text(&mut self, x: u16, y: u16, text: &str) {
+        for (i, ch) in text.chars().enumerate() {
+            let Ok(i) = u16::try_from(i) else {
+                break;
+            };
+            self.put(x.saturating_add(i), y, ch, rgb(TEXT_COLOR));
+        }
+    }
+
+    /// Writes `text` centred in the `width` columns from `x`.
+    fn centered(&mut self, x: u16, width: u16, y: u16, text: &str) {
+        let len = text.chars().count() as u16;
+        self.text(x + width.saturating_sub(len) / 2, y, text);
+    }
+
+    /// Writes out the cells that differ from `before`, or all of them.
+    fn show(&self, out: &mut impl Write, before: Option<&Canvas>) -> io::Result<()> {
+        let before = before.filter(|before| before.width == self.width);
+        if before.is_none() {
+            queue!(out, ResetColor, Clear(ClearType::All))?;
+        }
+
+        let mut colors = None;
+        for y in 0..self.height {
+            let mut at = None;
+            for x in 0..self.width {
+                let i = usize::from(y) * usize::from(self.width) + usize::from(x);
+                let cell = self.cells[i];
+                if before.and_then(|before| before.cells.get(i)) == Some(&cell) {
+                    continue;
+                }
+                if at != Some(x) {
+                    queue!(out, MoveTo(x, y))?;
+                }
+                if colors != Some((cell.fg, cell.bg)) {
+                    queue!(
+                        out,
+                        SetForegroundColor(term_color(cell.fg)),
+                        SetBackgroundColor(term_color(cell.bg))
+                    )?;
+                    colors = Some((cell.fg, cell.bg));
+                }
+                queue!(out, Print(cell.ch))?;
+                at = Some(x + 1);
+            }
+        }
+        queue!(out, ResetColor)?;
+        out.flush()
+    }
+}
+
+/// Lays out the HUD, the board with its edge, any menu and the status lines,
+/// centred across a `width` by `height` terminal.
+fn render(frame: &Frame, display: &DisplayConfig, width: u16, height: u16) -> Canvas {
+    let background = rgb(display.background.0);
+    let mut canvas = Canvas::new(width, height, background);
+
+    let settings = frame.settings;
+    let board_width = (settings.width as u16).saturating_mul(CELL_WIDTH) + 2;
+    let board_height = settings.height as u16 + 2;
+
+    // Wide enough for every line of text too, like the window's minimum size
+    let menu_lines = frame.menu.as_ref().map(menu_text).unwrap_or_default();
+    let text_width = frame
+        .hud
+        .iter()
+        .flatten()
+        .chain(&frame.status)
+        .chain(&menu_lines)
+        .map(|line| line.chars().count() as u16 + 2)
+        .max()
+        .unwrap_or(0);
+    let area_width = board_width.max(text_width);
+    let area_height = HUD_ROWS + board_height + frame.status.len() as u16;
+    if width < board_width || height < area_height {
+        let needed = format!("Make the terminal at least {}x{}", board_width, area_height);
+        canvas.text(0, 0, &needed);
+        return canvas;
+    }
+    let left = width.saturating_sub(area_width) / 2;
+    let area_width = area_width.min(width);
+
+    if let Some(lines) = &frame.hud {
+        canvas.fill(left, 0, area_width, HUD_ROWS, BACKGROUND_COLOR);
+        for (y, line) in lines.iter().enumerate() {
+            canvas.text(left + 1, y as u16, line);
+        }
+    }
+
+    let board_left = left + (area_width - board_width) / 2;
+    draw_board(&mut canvas, frame, display, board_left, HUD_ROWS);
+
+    let below = HUD_ROWS + board_height;
+    for (i, line) in frame.status.iter().enumerate() {
+        canvas.centered(left, area_width, below + i as u16, line);
+    }
+
+    if frame.menu.is_some() {
+        // Over the board, or further down if the board is too short for it
+        let panel_height = (menu_lines.len() as u16 + 2)
+            .max(board_height)
+            .min(height - HUD_ROWS);
+        canvas.fill(left, HUD_ROWS, area_width, panel_height, PANEL_COLOR);
+        let (body, hint) = menu_lines.split_at(menu_lines.len() - 1);
+        for (i, line) in body.iter().enumerate() {
+            canvas.centered(left, area_width, HUD_ROWS + 1 + i as u16, line);
+        }
+        canvas.centered(left, area_width, HUD_ROWS + panel_height - 2, &hint[0]);
+    }
+
+    canvas
+}
+
+/// A menu as lines of text, laid out like the window draws it, with the
+/// hint last.
+fn menu_text(menu: &Menu) -> Vec<String> {
+    let mut lines = vec![menu.title.clone(), String::new()];
+    lines.extend(menu.lines.iter().cloned());
+    if !menu.lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(menu.item_lines());
+    lines.push(String::new());
+    lines.push(menu.hint.clone());
+    lines
+}
+
+/// Draws the board with its edge, its top left corner at (`left`, `top`).
+fn draw_board(canvas: &mut Canvas, frame: &Frame, display: &DisplayConfig, left: u16, top: u16) {
+    let settings = frame.settings;
+    let (columns, rows) = (settings.width as u16, settings.height as u16);
+    let (width, height) = (columns * CELL_WIDTH + 2, rows + 2);
+
+    let edge = match settings.boundary {
+        BoundaryMode::Walls => WALL,
+        BoundaryMode::Wrap => WRAP_EDGE,
+    };
+    for x in 0..width {
+        canvas.put(left + x, top, edge, rgb(WALL_COLOR));
+        canvas.put(left + x, top + height - 1, edge, rgb(WALL_COLOR));
+    }
+    for y in 1..height - 1 {
+        canvas.put(left, top + y, edge, rgb(WALL_COLOR));
+        canvas.put(left + width - 1, top + y, edge, rgb(WALL_COLOR));
+    }
+
+    let background = rgb(display.background.0);
+    let mut fill = |position: Position, color: [f32; 4]| {
+        let x = left + 1 + position.x as u16 * CELL_WIDTH;
+        let color = blend(background, color);
+        for dx in 0..CELL_WIDTH {
+            canvas.put(x + dx, top + 1 + position.y as u16, CELL, color);
+        }
+    };
+    let tint = match frame.round {
+        Round::Empty => None,
+        Round::Game(game) => {
+            for segment in game.snake() {
+                fill(*segment, display.snake.0);
+            }
+            if !game.is_won() {
+                fill(game.food(), display.food.0);
+            }
+            if game.is_game_over() {
+                Some(GAME_OVER_TINT)
+            } else {
+                game.is_won().then_some(VICTORY_TINT)
+            }
+        }
+        Round::Versus(versus) => {
+            let colors = [display.snake.0, display.player2_snake.0];
+            for (player, mut color) in versus.players().iter().zip(colors) {
+                if !player.is_alive() {
+                    color[3] *= CRASHED_ALPHA;
+                }
+                for segment in player.snake() {
+                    fill(*segment, color);
+                }
+            }
+            if !versus.is_finished() {
+                fill(versus.food(), display.food.0);
+            }
+            versus.is_finished().then_some(ROUND_OVER_TINT)
+        }
+    };
+    if let Some(tint) = tint {
+        canvas.fill(left + 1, top + 1, columns * CELL_WIDTH, rows, tint);
+    }
+}
@@ -0,0 +1,484 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::food::Food;
+use crate::game::{
+    queue_turn, Direction, GameSettings, Position, Timestep, MAX_CATCH_UP_TICKS, MAX_QUEUED_INPUTS,
+};
+use crate::grid::Grid;
+use crate::stats::{GameStats, StatsFormat, VersusOutcome};
+
+/// Number of snakes in a versus round.
+pub const VERSUS_PLAYERS: usize = 2;
+
+/// One of the snakes in a [`Versus`] round.
+pub struct VersusPlayer {
+    snake: VecDeque<Position>,
+    direction: Direction,
+    input_queue: VecDeque<Direction>,
+    is_alive: bool,
+    score: u32,
+    stats: GameStats,
+}
+
+impl VersusPlayer {
+    fn new(
+        player: usize,
+        start: Position,
+        direction: Direction,
+        seed: u64,
+        settings: GameSettings,
+    ) -> Self {
+        let mut stats = GameStats::new(seed, settings);
+        stats.player = Some(player);
+        Self {
+            snake: VecDeque::from([start]),
+            direction,
+            input_queue: VecDeque::with_capacity(MAX_QUEUED_INPUTS),
+            is_alive: true,
+            score: 0,
+            stats,
+        }
+    }
+
+    /// Body segments, head first.
+    pub fn snake(&self) -> &VecDeque<Position> {
+        &self.snake
+    }
+
+    pub fn head(&self) -> Position {
+        self.snake[0]
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Whether the snake is still going; a crash ends the round.
+    pub fn is_alive(&self) -> bool {
+        self.is_alive
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn stats(&self) -> &GameStats {
+        &self.stats
+    }
+}
+
+/// One snake in a [`VersusSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerSnapshot {
+    /// Head first
+    pub snake: Vec<Position>,
+    pub direction: Direction,
+    /// Turns queued for the coming ticks
+    pub queued: Vec<Direction>,
+    pub is_alive: bool,
+    pub score: u32,
+    pub food_eaten: u32,
+    /// Up, down, left and right turns so far
+    pub turns: [u32; 4],
+    pub time_played: Duration,
+}
+
+/// A [`Versus`] round frozen between two ticks, with enough detail to carry
+/// on from it exactly, food placement included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersusSnapshot {
+    pub settings: GameSettings,
+    pub seed: u64,
+    /// How far the food generator has got, in 32-bit words
+    pub rng_word_pos: u128,
+    pub tick: u64,
+    pub food: Position,
+    /// Every free cell, in the order food is picked from. The order depends
+    /// on how the round got here, so it can't be worked out from the snakes.
+    pub free: Vec<Position>,
+    pub result: Option<RoundResult>,
+    pub players: [PlayerSnapshot; VERSUS_PLAYERS],
+}
+
+/// How a versus round ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundResult {
+    /// Index of the player who won
+    Winner(usize),
+    Draw,
+}
+
+impl RoundResult {
+    /// How the round went for `player`.
+    pub fn outcome(self, player: usize) -> VersusOutcome {
+        match self {
+            RoundResult::Winner(winner) if winner == player => VersusOutcome::Win,
+            RoundResult::Winner(_) => VersusOutcome::Loss,
+            RoundResult::Draw => VersusOutcome::Draw,
+        }
+    }
+}
+
+/// Two snakes on one board, racing for the same food. The round ends the
+/// tick a snake crashes, into a wall, into a body (its own or the other's),
+/// or head-on into the other snake.
+///
+/// The survivor wins. When both crash on the same tick, or the board fills
+/// up, the higher score wins and equal scores are a draw.
+pub struct Versus {
+    players: [VersusPlayer; VERSUS_PLAYERS],
+    grid: Grid,
+    food: Food,
+    result: Option<RoundResult>,
+    is_paused: bool,
+    speed: f64,
+    tick: u64,
+    timestep: Timestep,
+    settings: GameSettings,
+    seed: u64,
+}
+
+impl Versus {
+    /// # Panics
+    ///
+    /// If `settings` fail [`GameSettings::validate`].
+    pub fn with_settings(settings: GameSettings, seed: u64) -> Versus {
+        if let Err(e) = settings.validate() {
+            panic!("invalid game settings: {}", e);
+        }
+
+        // Side by side in the middle row, heading in opposite directions
+        let y = settings.height / 2;
+        let starts = [
+            (settings.width / 4, Direction::Up),
+            (settings.width - 1 - settings.width / 4, Direction::Down),
+        ];
+        let mut grid = Grid::new(settings.width, settings.height);
+        let players = [0, 1].map(|player| {
+            let (x, direction) = starts[player];
+            let start = Position { x, y };
+            grid.occupy(start);
+            VersusPlayer::new(player, start, direction, seed, settings)
+        });
+
+        Versus {
+            players,
+            food: Food::new(seed, &grid),
+            grid,
+            result: None,
+            is_paused: false,
+            speed: settings.speed.interval(0),
+            tick: 0,
+            timestep: Timestep::default(),
+            settings,
+            seed,
+        }
+    }
+
+    pub fn settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn players(&self) -> &[VersusPlayer; VERSUS_PLAYERS] {
+        &self.players
+    }
+
+    pub fn food(&self) -> Position {
+        self.food.position()
+    }
+
+    /// Whether any snake is on `position`.
+    pub fn is_occupied(&self, position: Position) -> bool {
+        self.grid.is_occupied(position)
+    }
+
+    /// How the round ended, or `None` while it is still being played.
+    pub fn result(&self) -> Option<RoundResult> {
+        self.result
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Pauses or resumes the round, see [`crate::Game::set_paused`].
+    pub fn set_paused(&mut self, paused: bool) {
+        self.is_paused = paused;
+    }
+
+    /// Seconds between two ticks, which follows the leading score.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Number of ticks simulated so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Writes each player's stats in `format` as a game of its own, see
+    /// [`GameStats::save`].
+    pub fn save_stats(&self, dir: &Path, format: StatsFormat) -> io::Result<Vec<PathBuf>> {
+        self.players
+            .iter()
+            .map(|player| {
+                player
+                    .stats
+                    .save(dir, format, player.score, player.snake.len())
+            })
+            .collect()
+    }
+
+    /// Queues a turn for `player`, with the same rules as
+    /// [`crate::Game::change_direction`]. Returns whether the turn was
+    /// queued.
+    ///
+    /// # Panics
+    ///
+    /// If `player` is not below [`VERSUS_PLAYERS`].
+    pub fn change_direction(&mut self, player: usize, new_direction: Direction) -> bool {
+        if self.is_paused || self.is_finished() {
+            return false;
+        }
+        let player = &mut self.players[player];
+        let queued = queue_turn(&mut player.input_queue, player.direction, new_direction);
+        if queued {
+            player.stats.count_turn(new_direction);
+        }
+        queued
+    }
+
+    /// Advances the clock by `dt` seconds and runs every tick that became
+    /// due, like [`crate::Game::update`]. Returns `true` when the board
+    /// changed.
+    pub fn update(&mut self, dt: f64) -> bool {
+        self.update_with(dt, |_| {})
+    }
+
+    /// Like [`Versus::update`], but calls `before_tick` ahead of every tick,
+    /// see [`crate::Game::update_with`].
+    pub fn update_with<F: FnMut(&mut Versus)>(&mut self, dt: f64, mut before_tick: F) -> bool {
+        if self.is_finished() || self.is_paused {
+            return false;
+        }
+
+        self.add_play_time(dt);
+
+        self.timestep.advance(dt);
+        let mut ticked = false;
+        while !self.is_finished() && self.timestep.take_tick(self.speed, MAX_CATCH_UP_TICKS) {
+            before_tick(self);
+            ticked |= self.step();
+        }
+
+        ticked
+    }
+
+    /// Everything about the round as it stands, see [`Versus::from_snapshot`].
+    pub fn snapshot(&self) -> VersusSnapshot {
+        VersusSnapshot {
+            settings: self.settings,
+            seed: self.seed,
+            rng_word_pos: self.food.word_pos(),
+            tick: self.tick,
+            food: self.food.position(),
+            free: self.grid.free().to_vec(),
+            result: self.result,
+            players: self.players.each_ref().map(|player| {
+                let stats = &player.stats;
+                PlayerSnapshot {
+                    snake: player.snake.iter().copied().collect(),
+                    direction: player.direction,
+                    queued: player.input_queue.iter().copied().collect(),
+                    is_alive: player.is_alive,
+                    score: player.score,
+                    food_eaten: stats.food_eaten,
+                    turns: [
+                        stats.up_turns,
+                        stats.down_turns,
+                        stats.left_turns,
+                        stats.right_turns,
+                    ],
+                    time_played: stats.time_played,
+                }
+            }),
+        }
+    }
+
+    /// Rebuilds a round from [`Versus::snapshot`]. From then on it plays out
+    /// exactly like the original given the same turns. Fails if the snapshot
+    /// describes an impossible board.
+    pub fn from_snapshot(snapshot: &VersusSnapshot) -> Result<Versus, String> {
+        let settings = snapshot.settings;
+        settings.validate()?;
+        let in_bounds =
+            |position: &Position| position.x < settings.width && position.y < settings.height;
+
+        let grid = Grid::with_free(settings.width, settings.height, &snapshot.free)
+            .ok_or("a free cell is out of place")?;
+        let mut taken = Grid::new(settings.width, settings.height);
+        let mut players = Vec::with_capacity(VERSUS_PLAYERS);
+        for (i, player) in snapshot.players.iter().enumerate() {
+            if player.snake.is_empty() {
+                return Err(format!("player {} has no snake", i + 1));
+            }
+            if player.queued.len() > MAX_QUEUED_INPUTS {
+                return Err(format!("player {} has too many turns queued", i + 1));
+            }
+            for segment in &player.snake {
+                if !in_bounds(segment) || !grid.is_occupied(*segment) || taken.is_occupied(*segment)
+                {
+                    return Err(format!("player {} has a segment out of place", i + 1));
+                }
+                taken.occupy(*segment);
+            }
+
+            let mut stats = GameStats::new(snapshot.seed, settings);
+            let [up, down, left, right] = player.turns;
+            (stats.up_turns, stats.down_turns) = (up, down);
+            (stats.left_turns, stats.right_turns) = (left, right);
+            stats.food_eaten = player.food_eaten;
+            stats.time_played = player.time_played;
+            stats.player = Some(i);
+            stats.versus_result = snapshot.result.map(|result| result.outcome(i));
+            players.push(VersusPlayer {
+                snake: player.snake.iter().copied().collect(),
+                direction: player.direction,
+                input_queue: player.queued.iter().copied().collect(),
+                is_alive: player.is_alive,
+                score: player.score,
+                stats,
+            });
+        }
+        if !in_bounds(&snapshot.food) {
+            return Err("food is off the board".to_string());
+        }
+        // Food is only left under a snake once the board is full and there
+        // is nowhere else to put it
+        if grid.is_occupied(snapshot.food) && !grid.free().is_empty() {
+            return Err("food is on a snake".to_string());
+        }
+        if taken.free().len() != grid.free().len() {
+            return Err("a cell is taken with no snake on it".to_string());
+        }
+
+        let best = players.iter().map(|player| player.score).max();
+        Ok(Versus {
+            players: players.try_into().ok().expect("one per snapshot player"),
+            grid,
+            food: Food::restore(snapshot.seed, snapshot.rng_word_pos, snapshot.food),
+            result: snapshot.result,
+            is_paused: false,
+            speed: settings.speed.interval(best.unwrap_or(0)),
+            tick: snapshot.tick,
+            timestep: Timestep::default(),
+            settings,
+            seed: snapshot.seed,
+        })
+    }
+
+    /// Counts `dt` seconds towards every player's time played, for peers
+    /// that follow another clock instead of calling [`Versus::update`].
+    pub(crate) fn add_play_time(&mut self, dt: f64) {
+        for player in &mut self.players {
+            player.stats.add_play_time(dt);
+        }
+    }
+
+    /// Moves both snakes one cell regardless of timing. Returns `false` if
+    /// the round had already finished.
+    pub fn step(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.tick += 1;
+
+        for player in &mut self.players {
+            if let Some(direction) = player.input_queue.pop_front() {
+                player.direction = direction;
+            }
+        }
+
+        // Every head is checked against the board as it was before anyone
+        // moved, so running into the other snake's head counts as a body hit
+        let heads = self
+            .players
+            .each_ref()
+            .map(|player| self.settings.neighbor(player.head(), player.direction));
+        let mut crashed = heads.map(|head| head.is_none_or(|head| self.grid.is_occupied(head)));
+        if heads[0].is_some() && heads[0] == heads[1] {
+            // Head-to-head into the same cell takes both snakes out
+            crashed = [true, true];
+        }
+
+        if crashed.contains(&true) {
+            for (player, crashed) in self.players.iter_mut().zip(crashed) {
+                player.is_alive = !crashed;
+            }
+            self.finish(match crashed {
+                [true, false] => RoundResult::Winner(1),
+                [false, true] => RoundResult::Winner(0),
+                _ => self.leader(),
+            });
+            return true;
+        }
+
+        let mut ate = false;
+        for (player, head) in self.players.iter_mut().zip(heads) {
+            let head = head.expect("crashes are handled above");
+            player.snake.push_front(head);
+            self.grid.occupy(head);
+
+            if head == self.food.position() {
+                player.score += 1;
+                player.stats.food_eaten += 1;
+                ate = true;
+            } else if let Some(tail) = player.snake.pop_back() {
+                self.grid.release(tail);
+            }
+        }
+
+        if ate {
+            let best = self.players.iter().map(|player| player.score).max();
+            self.speed = self.settings.speed.interval(best.unwrap_or(0));
+            if !self.food.respawn(&self.grid) {
+                // Nowhere left to put food, so the scores decide
+                for player in &mut self.players {
+                    player.stats.board_cleared = true;
+                }
+                self.finish(self.leader());
+            }
+        }
+
+        true
+    }
+
+    /// Ends the round with `result` and notes it in every player's stats.
+    fn finish(&mut self, result: RoundResult) {
+        self.result = Some(result);
+        for (i, player) in self.players.iter_mut().enumerate() {
+            player.stats.versus_result = Some(result.outcome(i));
+        }
+    }
+
+    /// The player with the higher score, or a draw on equal scores.
+    fn leader(&self) -> RoundResult {
+        let [first, second] = self.players.each_ref().map(|player| player.score);
+        match first.cmp(&second) {
+            std::cmp::Ordering::Greater => RoundResult::Winner(0),
+            std::cmp::Ordering::Less => RoundResult::Winner(1),
+            std::cmp::Ordering::Equal => RoundResult::Draw,
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Fixtures shared by the integration tests.
+
+use rust_snake_game::{BoundaryMode, GameSettings};
+
+/// A small board, so games on it fill up and end quickly.
+pub fn settings(boundary: BoundaryMode) -> GameSettings {
+    GameSettings {
+        width: 12,
+        height: 10,
+        boundary,
+        ..GameSettings::default()
+    }
+}
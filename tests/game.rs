@@ -0,0 +1,202 @@
+//! Headless games on fixed seeds, checked against the exact boards they
+//! have always produced.
+
+use rust_snake_game::{
+    BotKind, BoundaryMode, Difficulty, Direction, Game, GameSettings, Position, SpeedCurve,
+    MAX_CATCH_UP_TICKS,
+};
+
+mod common;
+
+use common::settings;
+
+#[test]
+fn food_follows_the_seed() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 42);
+    assert_eq!(game.head(), Position { x: 6, y: 5 });
+    assert_eq!(game.food(), Position { x: 2, y: 2 });
+
+    // Straight to the food: up three, left four
+    game.change_direction(Direction::Up);
+    for _ in 0..3 {
+        game.step();
+    }
+    game.change_direction(Direction::Left);
+    for _ in 0..4 {
+        game.step();
+    }
+    assert_eq!(game.tick(), 7);
+    assert_eq!(game.head(), Position { x: 2, y: 2 });
+    assert_eq!(game.score(), 1);
+    assert_eq!(game.snake().len(), 2);
+    assert_eq!(game.food(), Position { x: 8, y: 6 });
+    assert_eq!(game.speed(), 0.098);
+}
+
+#[test]
+fn the_clock_runs_whole_ticks_and_drops_a_long_backlog() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 42);
+    // 100 ms per tick; time left over carries on to the next update
+    assert!(!game.update(0.06));
+    assert!(game.update(0.06));
+    assert_eq!(game.tick(), 1);
+
+    // A long frame only catches up a few ticks, and the rest is dropped
+    assert!(game.update(10.0));
+    assert_eq!(game.tick(), 1 + u64::from(MAX_CATCH_UP_TICKS));
+    assert!(!game.update(0.05));
+    assert_eq!(game.tick(), 1 + u64::from(MAX_CATCH_UP_TICKS));
+}
+
+#[test]
+fn harder_presets_are_faster_and_all_level_off() {
+    let curves: Vec<SpeedCurve> = Difficulty::PRESETS
+        .iter()
+        .map(|difficulty| difficulty.speed_curve().unwrap())
+        .collect();
+    for pair in curves.windows(2) {
+        assert!(pair[1].interval(0) < pair[0].interval(0));
+        assert!(pair[1].minimum_ms < pair[0].minimum_ms);
+    }
+    for curve in &curves {
+        assert_eq!(curve.validate(), Ok(()));
+        assert!(curve.interval(1) < curve.interval(0));
+        assert_eq!(curve.interval(u32::MAX), curve.minimum_ms as f64 / 1000.0);
+    }
+
+    // Custom has no curve of its own, so picking it changes nothing
+    let mut settings = settings(BoundaryMode::Wrap);
+    settings.set_difficulty(Difficulty::Insane);
+    settings.set_difficulty(Difficulty::Custom);
+    assert_eq!(settings.difficulty, Difficulty::Insane);
+    assert_eq!(settings.speed, Difficulty::Insane.speed_curve().unwrap());
+}
+
+#[test]
+fn turns_queue_up_within_a_tick() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 42);
+    // Reversing, repeating the last queued turn and a fourth queued turn are
+    // all dropped
+    game.change_direction(Direction::Left);
+    game.change_direction(Direction::Up);
+    game.change_direction(Direction::Up);
+    game.change_direction(Direction::Left);
+    game.change_direction(Direction::Down);
+    game.change_direction(Direction::Right);
+    assert_eq!(game.stats().total_turns(), 3);
+    assert_eq!(game.replay().inputs.len(), 3);
+
+    let mut heads = Vec::new();
+    for _ in 0..4 {
+        game.step();
+        heads.push(game.head());
+    }
+    assert_eq!(
+        heads,
+        [
+            Position { x: 6, y: 4 },
+            Position { x: 5, y: 4 },
+            Position { x: 5, y: 5 },
+            Position { x: 5, y: 6 },
+        ]
+    );
+    assert_eq!(game.direction(), Direction::Down);
+}
+
+#[test]
+fn paused_games_ignore_turns() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 42);
+    game.set_paused(true);
+    game.change_direction(Direction::Up);
+    assert!(!game.update(1.0));
+    game.set_paused(false);
+    game.step();
+    assert_eq!(game.head(), Position { x: 7, y: 5 });
+    assert_eq!(game.stats().total_turns(), 0);
+}
+
+#[test]
+fn walls_end_the_game_and_wrap_does_not() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Walls), 42);
+    while game.step() {}
+    assert!(game.is_game_over());
+    assert!(!game.is_won());
+    assert_eq!(game.tick(), 6);
+    assert_eq!(game.head(), Position { x: 11, y: 5 });
+    assert!(!game.step());
+
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 42);
+    for _ in 0..8 {
+        game.step();
+    }
+    assert!(!game.is_finished());
+    assert_eq!(game.head(), Position { x: 2, y: 5 });
+}
+
+#[test]
+fn clearing_the_board_wins() {
+    let settings = GameSettings {
+        width: 4,
+        height: 4,
+        ..GameSettings::default()
+    };
+    let mut game = Game::with_settings(settings, 5);
+    let mut bot = BotKind::Hamiltonian.build(&settings).unwrap();
+    while !game.is_finished() {
+        bot.drive(&mut game);
+        game.step();
+    }
+    assert!(game.is_won());
+    assert!(!game.is_game_over());
+    assert_eq!(game.tick(), 52);
+    assert_eq!(game.score(), 15);
+    assert_eq!(game.snake().len(), 16);
+    assert!(game.stats().board_cleared);
+    assert!(!game.step());
+}
+
+#[test]
+fn settings_are_validated() {
+    let base = settings(BoundaryMode::Wrap);
+    assert_eq!(base.validate(), Ok(()));
+
+    let too_narrow = GameSettings { width: 3, ..base };
+    assert_eq!(
+        too_narrow.validate().unwrap_err(),
+        "board width must be between 4 and 1024 cells, got 3"
+    );
+    let too_tall = GameSettings {
+        height: 1025,
+        ..base
+    };
+    assert_eq!(
+        too_tall.validate().unwrap_err(),
+        "board height must be between 4 and 1024 cells, got 1025"
+    );
+
+    let mut wrong_curve = base;
+    wrong_curve.speed.initial_ms = 80;
+    assert_eq!(
+        wrong_curve.validate().unwrap_err(),
+        "speed curve does not match the normal difficulty"
+    );
+
+    let mut custom = base;
+    custom.set_custom_speed(SpeedCurve {
+        minimum_ms: 0,
+        ..SpeedCurve::default()
+    });
+    assert_eq!(
+        custom.validate().unwrap_err(),
+        "minimum_ms must be greater than 0"
+    );
+    custom.set_custom_speed(SpeedCurve {
+        initial_ms: 40,
+        step_ms: 2,
+        minimum_ms: 50,
+    });
+    assert_eq!(
+        custom.validate().unwrap_err(),
+        "minimum_ms (50) must not be greater than initial_ms (40)"
+    );
+}
@@ -0,0 +1,101 @@
+//! High score tables ranked in memory and read back from disk.
+
+use std::fs;
+
+use rust_snake_game::{
+    BoundaryMode, Difficulty, HighScore, HighScoreTable, ScoreCategory, MAX_HIGH_SCORES,
+};
+
+mod common;
+
+use common::settings;
+
+fn entry(name: &str, score: u32) -> HighScore {
+    HighScore {
+        name: name.to_string(),
+        score,
+        length: score as usize + 1,
+        seed: 1,
+        timestamp: 0,
+    }
+}
+
+#[test]
+fn scores_are_ranked_and_ties_go_below() {
+    let category = ScoreCategory::new(settings(BoundaryMode::Wrap));
+    let mut table = HighScoreTable::default();
+
+    assert_eq!(table.insert(&category, entry("ann", 5)), Some(0));
+    assert_eq!(table.insert(&category, entry("bob", 9)), Some(0));
+    assert_eq!(table.insert(&category, entry("cat", 5)), Some(2));
+    assert_eq!(table.insert(&category, entry("dan", 0)), None);
+
+    let names: Vec<&str> = table
+        .entries(&category)
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect();
+    assert_eq!(names, ["bob", "ann", "cat"]);
+    assert_eq!(table.best(&category), 9);
+}
+
+#[test]
+fn a_full_table_only_takes_better_scores() {
+    let category = ScoreCategory::new(settings(BoundaryMode::Wrap));
+    let mut table = HighScoreTable::default();
+    for score in 1..=MAX_HIGH_SCORES as u32 {
+        table.insert(&category, entry("ann", score));
+    }
+
+    assert!(!table.qualifies(&category, 1));
+    assert_eq!(table.insert(&category, entry("bob", 1)), None);
+    assert_eq!(
+        table.insert(&category, entry("bob", 2)),
+        Some(MAX_HIGH_SCORES - 1)
+    );
+    assert_eq!(table.entries(&category).len(), MAX_HIGH_SCORES);
+    assert_eq!(table.entries(&category).last().unwrap().name, "bob");
+}
+
+#[test]
+fn each_board_and_difficulty_has_its_own_table() {
+    let wrap = ScoreCategory::new(settings(BoundaryMode::Wrap));
+    let walls = ScoreCategory::new(settings(BoundaryMode::Walls));
+    let mut hard = settings(BoundaryMode::Wrap);
+    hard.set_difficulty(Difficulty::Hard);
+    let hard = ScoreCategory::new(hard);
+
+    let mut table = HighScoreTable::default();
+    table.insert(&wrap, entry("ann", 3));
+    assert_eq!(table.best(&wrap), 3);
+    assert_eq!(table.best(&walls), 0);
+    assert_eq!(table.best(&hard), 0);
+    assert_eq!(wrap.key(), "wrap 12x10 normal");
+}
+
+#[test]
+fn tables_survive_saving_and_old_tables_count_as_normal() {
+    let dir = std::env::temp_dir().join(format!("snake_highscores_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("highscores.json");
+
+    let category = ScoreCategory::new(settings(BoundaryMode::Walls));
+    let mut table = HighScoreTable::default();
+    table.insert(&category, entry("ann", 4));
+    table.save(&path).unwrap();
+    assert_eq!(
+        HighScoreTable::load(&path).unwrap().entries(&category),
+        [entry("ann", 4)]
+    );
+
+    // Saved before difficulties existed
+    fs::write(
+        &path,
+        r#"{"categories": {"walls 12x10": [{"name": "bob", "score": 2, "length": 3, "seed": 1, "timestamp": 0}]}}"#,
+    )
+    .unwrap();
+    let table = HighScoreTable::load(&path).unwrap();
+    assert_eq!(table.best(&category), 2);
+    assert!(HighScoreTable::load(&dir.join("missing.json")).is_ok());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,540 @@
+//! Host and players talking over localhost, with the players scripted.
+
+use std::io::{self, Cursor, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snake_game::{
+    BoundaryMode, Client, Direction, GameSettings, Host, HostSession, Message, Role, Spectator,
+    Versus, GUEST_PLAYER, HOST_PLAYER, LONG_FRAME, MAX_NEWCOMERS, MAX_PEER_FRAME_LEN,
+    PROTOCOL_VERSION,
+};
+
+mod common;
+
+use common::settings;
+
+/// A host on a free localhost port.
+fn host(settings: GameSettings, seed: u64) -> Host {
+    Host::bind("127.0.0.1:0", settings, seed).unwrap()
+}
+
+/// Lets the client take every tick the host has sent so far.
+fn catch_up(client: &mut Client, tick: u64) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.versus().tick() < tick {
+        assert!(Instant::now() < deadline, "client never caught up");
+        client.update(0.0).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Lets the spectator take every tick the host has sent so far.
+fn watch(spectator: &mut Spectator, tick: u64) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while spectator.versus().tick() < tick {
+        assert!(Instant::now() < deadline, "spectator never caught up");
+        spectator.update(0.0).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Polls the host until `spectators` are connected.
+fn wait_for_spectators(session: &mut HostSession, spectators: usize) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while session.spectators() < spectators {
+        assert!(Instant::now() < deadline, "spectators never arrived");
+        session.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Both players turn on a fixed script, every third tick.
+fn play_scripted(session: &mut HostSession, client: &mut Client, ticks: u64) {
+    let script = [
+        Direction::Left,
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+    ];
+    let end = session.versus().tick() + ticks;
+    while !session.versus().is_finished() && session.versus().tick() < end {
+        let tick = session.versus().tick();
+        if tick.is_multiple_of(3) {
+            let i = (tick / 3) as usize;
+            session.turn(script[i % script.len()]);
+            client.turn(script[(i + 1) % script.len()]).unwrap();
+            // Give the guest's turn time to reach the host before the tick
+            thread::sleep(Duration::from_millis(2));
+        }
+        session.step().unwrap();
+        catch_up(client, session.versus().tick());
+        assert_same_round(session.versus(), client.versus());
+    }
+}
+
+fn assert_same_round(a: &Versus, b: &Versus) {
+    assert_eq!(a.tick(), b.tick());
+    assert_eq!(a.food(), b.food());
+    assert_eq!(a.result(), b.result());
+    for (a, b) in a.players().iter().zip(b.players()) {
+        assert_eq!(a.snake(), b.snake());
+        assert_eq!(a.score(), b.score());
+        assert_eq!(a.is_alive(), b.is_alive());
+        assert_eq!(a.stats().total_turns(), b.stats().total_turns());
+    }
+}
+
+#[test]
+fn messages_survive_the_wire() {
+    let mut round = Versus::with_settings(settings(BoundaryMode::Wrap), 9);
+    round.change_direction(GUEST_PLAYER, Direction::Left);
+    for _ in 0..5 {
+        round.step();
+    }
+    round.change_direction(GUEST_PLAYER, Direction::Up);
+
+    let messages = [
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            role: Role::Player,
+        },
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            role: Role::Spectator,
+        },
+        Message::Welcome {
+            player: 1,
+            seed: u64::MAX,
+            settings: settings(BoundaryMode::Walls),
+        },
+        Message::Reject {
+            reason: "full".to_string(),
+        },
+        Message::Turn {
+            direction: Direction::Left,
+        },
+        Message::Tick {
+            tick: 42,
+            turns: vec![(0, Direction::Up), (1, Direction::Right)],
+        },
+        Message::Snapshot {
+            round: Box::new(round.snapshot()),
+        },
+    ];
+
+    let mut wire = Vec::new();
+    for message in &messages {
+        message.write_to(&mut wire).unwrap();
+    }
+    let mut reader = Cursor::new(wire);
+    for message in &messages {
+        assert_eq!(&Message::read_from(&mut reader).unwrap(), message);
+    }
+}
+
+#[test]
+fn bad_frames_are_refused() {
+    // A Hello without the magic bytes
+    let mut reader = Cursor::new(vec![0, 8, 1, b'N', b'O', b'P', b'E', 0, 2, 0]);
+    let error = Message::read_from(&mut reader).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    // A Welcome for a board too small to play on
+    let mut wire = Vec::new();
+    Message::Welcome {
+        player: 1,
+        seed: 1,
+        settings: settings(BoundaryMode::Wrap),
+    }
+    .write_to(&mut wire)
+    .unwrap();
+    wire[2 + 1 + 1 + 8 + 1 + 3] = 1; // low byte of the width
+    let error = Message::read_from(&mut Cursor::new(wire)).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn long_frames_are_refused_before_they_are_read() {
+    // Claims a frame of 1 MiB, then ends
+    let mut header = LONG_FRAME.to_be_bytes().to_vec();
+    header.extend_from_slice(&(1u32 << 20).to_be_bytes());
+    let error = Message::read_limited(&mut Cursor::new(&header), MAX_PEER_FRAME_LEN).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    // Everything a peer sends the host fits
+    let mut wire = Vec::new();
+    Message::Hello {
+        version: PROTOCOL_VERSION,
+        role: Role::Player,
+    }
+    .write_to(&mut wire)
+    .unwrap();
+    Message::read_limited(&mut Cursor::new(wire), MAX_PEER_FRAME_LEN).unwrap();
+}
+
+/// Whether the host has hung up on `stream`, waiting a little for it to.
+fn hung_up(mut stream: &TcpStream) -> bool {
+    stream
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    match stream.read(&mut [0; 1]) {
+        Ok(read) => read == 0,
+        Err(e) => !matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ),
+    }
+}
+
+#[test]
+fn host_hangs_up_on_a_stranger_announcing_a_long_frame() {
+    let mut host = host(settings(BoundaryMode::Wrap), 31);
+    let mut stranger = TcpStream::connect(host.local_addr().unwrap()).unwrap();
+    stranger.write_all(&LONG_FRAME.to_be_bytes()).unwrap();
+    stranger.write_all(&(1u32 << 20).to_be_bytes()).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+    assert!(host.try_accept().unwrap().is_none());
+    thread::sleep(Duration::from_millis(20));
+    assert!(host.try_accept().unwrap().is_none());
+    assert!(hung_up(&stranger));
+}
+
+#[test]
+fn host_keeps_a_limited_number_of_connections_waiting() {
+    let mut host = host(settings(BoundaryMode::Wrap), 37);
+    let address = host.local_addr().unwrap();
+
+    let silent: Vec<TcpStream> = (0..MAX_NEWCOMERS + 3)
+        .map(|_| TcpStream::connect(address).unwrap())
+        .collect();
+    thread::sleep(Duration::from_millis(20));
+    assert!(host.try_accept().unwrap().is_none());
+
+    let closed = silent.iter().filter(|stream| hung_up(stream)).count();
+    assert_eq!(closed, 3);
+}
+
+#[test]
+fn guest_stays_in_lockstep_with_the_host() {
+    let mut host = host(settings(BoundaryMode::Wrap), 7);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let mut client = guest.join().unwrap();
+    assert_eq!(client.player(), GUEST_PLAYER);
+    assert_same_round(session.versus(), client.versus());
+
+    play_scripted(&mut session, &mut client, 500);
+
+    let turns: u32 = client
+        .versus()
+        .players()
+        .iter()
+        .map(|player| player.stats().total_turns())
+        .sum();
+    assert!(turns > 0);
+}
+
+#[test]
+fn guest_follows_the_host_clock() {
+    let mut host = host(settings(BoundaryMode::Wrap), 11);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let mut client = guest.join().unwrap();
+
+    // A second of play at 100 ms per tick
+    for _ in 0..100 {
+        session.update(0.01).unwrap();
+    }
+    assert!(session.versus().tick() >= 9);
+    catch_up(&mut client, session.versus().tick());
+    assert_same_round(session.versus(), client.versus());
+}
+
+#[test]
+fn host_refuses_other_protocol_versions() {
+    let mut host = host(settings(BoundaryMode::Wrap), 1);
+    let address = host.local_addr().unwrap();
+    let guests = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        Message::Hello {
+            version: PROTOCOL_VERSION + 1,
+            role: Role::Player,
+        }
+        .write_to(&mut stream)
+        .unwrap();
+        let reply = Message::read_from(&mut stream).unwrap();
+        // The host is still waiting for someone who can play
+        (reply, Client::connect(address).unwrap())
+    });
+
+    let session = host.accept().unwrap();
+    let (reply, client) = guests.join().unwrap();
+    assert!(matches!(reply, Message::Reject { .. }));
+    assert_same_round(session.versus(), client.versus());
+}
+
+#[test]
+fn silent_connection_does_not_hold_up_the_host() {
+    let mut host = host(settings(BoundaryMode::Wrap), 29);
+    let address = host.local_addr().unwrap();
+
+    // Connects but never says hello
+    let _silent = TcpStream::connect(address).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    let started = Instant::now();
+    assert!(host.try_accept().unwrap().is_none());
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let session = host.accept().unwrap();
+    let client = guest.join().unwrap();
+    assert_same_round(session.versus(), client.versus());
+}
+
+#[test]
+fn host_notices_the_guest_leaving() {
+    let mut host = host(settings(BoundaryMode::Wrap), 3);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session: HostSession = host.accept().unwrap();
+    drop(guest.join().unwrap());
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match session.poll() {
+            Ok(()) => assert!(Instant::now() < deadline, "host never noticed"),
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::ConnectionAborted);
+                break;
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn host_gives_up_on_a_guest_who_stops_reading() {
+    let mut host = host(settings(BoundaryMode::Wrap), 3);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            role: Role::Player,
+        }
+        .write_to(&mut stream)
+        .unwrap();
+        // Takes the welcome, then never reads again
+        Message::read_from(&mut stream).unwrap();
+        stream
+    });
+    let mut session = host.accept().unwrap();
+    let _stream = guest.join().unwrap();
+
+    // Nobody turns and the food is out of both snakes' way, so the round
+    // runs until the guest's buffers fill up
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let error = loop {
+        assert!(Instant::now() < deadline, "host never gave up");
+        match session.step() {
+            Ok(ticked) => assert!(ticked, "round ended before the buffers filled"),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn spectator_who_stops_reading_does_not_hold_up_the_round() {
+    let mut host = host(settings(BoundaryMode::Wrap), 3);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let _client = guest.join().unwrap();
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    Message::Hello {
+        version: PROTOCOL_VERSION,
+        role: Role::Spectator,
+    }
+    .write_to(&mut stream)
+    .unwrap();
+    wait_for_spectators(&mut session, 1);
+
+    // The guest's reader thread keeps taking ticks while the spectator's
+    // buffers fill up, after which they are dropped without a tick stalling
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while session.spectators() > 0 {
+        assert!(Instant::now() < deadline, "spectator never dropped");
+        let started = Instant::now();
+        assert!(
+            session.step().unwrap(),
+            "round ended before the buffers filled"
+        );
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
+
+#[test]
+fn guest_sees_the_last_tick_after_the_host_hangs_up() {
+    let settings = settings(BoundaryMode::Walls);
+    let mut host = host(settings, 5);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let mut client = guest.join().unwrap();
+
+    // Nobody turns, so both snakes run into the walls
+    while session.step().unwrap() {}
+    let last_tick = session.versus().tick();
+    let result = session.versus().result();
+    drop(session);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.versus().tick() < last_tick {
+        assert!(Instant::now() < deadline, "client never caught up");
+        if client.update(0.0).is_err() {
+            break;
+        }
+    }
+    assert_eq!(client.versus().tick(), last_tick);
+    assert_eq!(client.versus().result(), result);
+}
+
+#[test]
+fn spectator_joining_mid_round_sees_the_same_round() {
+    let mut host = host(settings(BoundaryMode::Wrap), 13);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let mut client = guest.join().unwrap();
+    play_scripted(&mut session, &mut client, 20);
+    let food_before = session.versus().food();
+
+    let watcher = thread::spawn(move || Spectator::connect(address).unwrap());
+    wait_for_spectators(&mut session, 1);
+    session.step().unwrap();
+    let mut spectator = watcher.join().unwrap();
+    watch(&mut spectator, session.versus().tick());
+    assert_same_round(session.versus(), spectator.versus());
+
+    // Food placed after the snapshot only matches if the spectator's rng
+    // picked up where the host's was, so the host heads for the food
+    let score = session.versus().players()[HOST_PLAYER].score();
+    while !session.versus().is_finished()
+        && session.versus().players()[HOST_PLAYER].score() < score + 2
+    {
+        let head = session.versus().players()[HOST_PLAYER].head();
+        let food = session.versus().food();
+        session.turn(if head.x < food.x {
+            Direction::Right
+        } else if head.x > food.x {
+            Direction::Left
+        } else if head.y < food.y {
+            Direction::Down
+        } else {
+            Direction::Up
+        });
+        session.step().unwrap();
+        watch(&mut spectator, session.versus().tick());
+        assert_same_round(session.versus(), spectator.versus());
+    }
+    assert_ne!(session.versus().food(), food_before);
+    assert_eq!(session.spectators(), 1);
+}
+
+#[test]
+fn spectator_waits_for_the_round_to_start() {
+    let mut host = host(settings(BoundaryMode::Wrap), 17);
+    let address = host.local_addr().unwrap();
+    let watcher = thread::spawn(move || Spectator::connect(address).unwrap());
+    thread::sleep(Duration::from_millis(20));
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let mut client = guest.join().unwrap();
+    wait_for_spectators(&mut session, 1);
+
+    session.step().unwrap();
+    let mut spectator = watcher.join().unwrap();
+    play_scripted(&mut session, &mut client, 30);
+    watch(&mut spectator, session.versus().tick());
+    assert_same_round(session.versus(), spectator.versus());
+}
+
+#[test]
+fn host_turns_away_a_third_player() {
+    let mut host = host(settings(BoundaryMode::Wrap), 19);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let _client = guest.join().unwrap();
+
+    let latecomer = thread::spawn(move || Client::connect(address));
+    while !latecomer.is_finished() {
+        session.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    let error = latecomer.join().unwrap().err().expect("should be refused");
+    assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+    assert_eq!(session.spectators(), 0);
+}
+
+#[test]
+fn silent_connection_does_not_hold_up_the_round() {
+    let mut host = host(settings(BoundaryMode::Wrap), 23);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let _client = guest.join().unwrap();
+
+    // Connects but never says hello
+    let _silent = TcpStream::connect(address).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    let started = Instant::now();
+    session.update(0.016).unwrap();
+    session.step().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // Spectators arriving after it still get in
+    let watcher = thread::spawn(move || Spectator::connect(address).unwrap());
+    wait_for_spectators(&mut session, 1);
+    session.step().unwrap();
+    let mut spectator = watcher.join().unwrap();
+    watch(&mut spectator, session.versus().tick());
+    assert_same_round(session.versus(), spectator.versus());
+}
+
+#[test]
+fn spectator_sees_the_end_after_the_guest_leaves() {
+    let settings = settings(BoundaryMode::Walls);
+    let mut host = host(settings, 31);
+    let address = host.local_addr().unwrap();
+    let guest = thread::spawn(move || Client::connect(address).unwrap());
+    let mut session = host.accept().unwrap();
+    let client = guest.join().unwrap();
+
+    // Nobody turns, so both snakes run into the walls
+    while session.step().unwrap() {}
+    drop(client);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while session.update(0.016).is_ok() {
+        assert!(Instant::now() < deadline, "host never noticed");
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let watcher = thread::spawn(move || Spectator::connect(address).unwrap());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !watcher.is_finished() {
+        assert!(Instant::now() < deadline, "spectator never got in");
+        let _ = session.update(0.016);
+        thread::sleep(Duration::from_millis(1));
+    }
+    let spectator = watcher.join().unwrap();
+    assert!(spectator.versus().is_finished());
+    assert_same_round(session.versus(), spectator.versus());
+}
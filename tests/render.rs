@@ -0,0 +1,76 @@
+//! Boards rasterised without a window, checked pixel by pixel.
+
+use image::Rgba;
+use rust_snake_game::{render_game, BoundaryMode, Game, GameSettings, Position, RenderStyle};
+
+mod common;
+
+use common::settings;
+
+/// A cell with neither snake nor food on it.
+fn empty_cell(game: &Game) -> (u32, u32) {
+    let settings = game.settings();
+    (0..settings.width)
+        .flat_map(|x| (0..settings.height).map(move |y| Position { x, y }))
+        .find(|cell| !game.snake().contains(cell) && *cell != game.food())
+        .map(|cell| (cell.x, cell.y))
+        .unwrap()
+}
+
+#[test]
+fn board_is_drawn_cell_by_cell() {
+    let game = Game::with_settings(settings(BoundaryMode::Wrap), 1);
+    let style = RenderStyle {
+        cell_size: 4,
+        ..RenderStyle::default()
+    };
+    let image = render_game(&game, &style);
+    assert_eq!(image.dimensions(), (48, 40));
+
+    let centre = |(x, y): (u32, u32)| *image.get_pixel(x * 4 + 2, y * 4 + 2);
+    let (head, food) = (game.snake()[0], game.food());
+    assert_eq!(centre((head.x, head.y)), Rgba([0, 255, 0, 255]));
+    assert_eq!(centre((food.x, food.y)), Rgba([255, 0, 0, 255]));
+    assert_eq!(centre(empty_cell(&game)), Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn lost_games_are_tinted_red_inside_the_walls() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Walls), 2);
+    while game.step() {}
+    assert!(game.is_game_over());
+
+    let image = render_game(&game, &RenderStyle::default());
+    // Grey wall, then the black board, each under half-strength red
+    assert_eq!(*image.get_pixel(0, 0), Rgba([192, 64, 64, 255]));
+    let (x, y) = empty_cell(&game);
+    assert_eq!(
+        *image.get_pixel(x * 25 + 12, y * 25 + 12),
+        Rgba([128, 0, 0, 255])
+    );
+}
+
+#[test]
+fn oversized_images_are_refused() {
+    let huge = GameSettings {
+        width: 1024,
+        height: 1024,
+        ..GameSettings::default()
+    };
+    let style = RenderStyle {
+        cell_size: 100,
+        ..RenderStyle::default()
+    };
+    let error = style.image_size(&huge).unwrap_err();
+    assert!(error.contains("102400x102400"), "{}", error);
+
+    let style = RenderStyle {
+        cell_size: 8,
+        ..RenderStyle::default()
+    };
+    assert_eq!(style.image_size(&huge), Ok((8192, 8192)));
+    assert_eq!(
+        RenderStyle::default().image_size(&settings(BoundaryMode::Wrap)),
+        Ok((300, 250))
+    );
+}
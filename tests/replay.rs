@@ -0,0 +1,109 @@
+//! Games played out and then replayed from their recorded inputs.
+
+use rust_snake_game::{BotKind, BoundaryMode, Direction, Game, Replay, ReplayPlayer};
+
+mod common;
+
+use common::settings;
+
+/// Replays `game` tick by tick and checks it ends up on the same board.
+fn assert_replays(game: &Game) {
+    let mut player = ReplayPlayer::new(game.replay());
+    while player.game().tick() < game.tick() {
+        assert!(player.step(), "replay finished early");
+    }
+    assert_same_game(player.game(), game);
+}
+
+fn assert_same_game(a: &Game, b: &Game) {
+    assert_eq!(a.tick(), b.tick());
+    assert_eq!(a.snake(), b.snake());
+    assert_eq!(a.food(), b.food());
+    assert_eq!(a.score(), b.score());
+    assert_eq!(a.is_game_over(), b.is_game_over());
+    assert_eq!(a.is_won(), b.is_won());
+}
+
+#[test]
+fn queued_turns_replay_exactly() {
+    let script = [
+        [Direction::Up, Direction::Left, Direction::Down],
+        [Direction::Right, Direction::Up, Direction::Right],
+        [Direction::Down, Direction::Left, Direction::Left],
+        [Direction::Up, Direction::Down, Direction::Right],
+    ];
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 21);
+    let mut turns = script.iter().cycle();
+    while !game.is_finished() && game.tick() < 400 {
+        // Several turns buffered within one tick, every fifth tick
+        if game.tick().is_multiple_of(5) {
+            for &direction in turns.next().unwrap() {
+                game.change_direction(direction);
+            }
+        }
+        game.step();
+    }
+    assert!(game.replay().inputs.len() > 10);
+    assert_replays(&game);
+}
+
+#[test]
+fn bot_games_replay_exactly() {
+    for (kind, boundary) in [
+        (BotKind::Pathfinding, BoundaryMode::Wrap),
+        (BotKind::Pathfinding, BoundaryMode::Walls),
+        (BotKind::Hamiltonian, BoundaryMode::Walls),
+    ] {
+        let settings = settings(boundary);
+        let mut game = Game::with_settings(settings, 7);
+        let mut bot = kind.build(&settings).unwrap();
+        // Played on the clock, the way the window and the bot command do
+        while !game.is_finished() && game.tick() < 3000 {
+            game.update_with(0.05, |game| bot.drive(game));
+        }
+        assert!(game.score() > 10, "{} barely played", kind);
+        assert_replays(&game);
+    }
+}
+
+#[test]
+fn replays_survive_saving() {
+    let mut game = Game::with_settings(settings(BoundaryMode::Wrap), 33);
+    let mut bot = BotKind::Pathfinding
+        .build(&settings(BoundaryMode::Wrap))
+        .unwrap();
+    while !game.is_finished() && game.tick() < 200 {
+        bot.drive(&mut game);
+        game.step();
+    }
+
+    let path = std::env::temp_dir().join(format!("snake_replay_{}.replay", std::process::id()));
+    game.replay().save(&path).unwrap();
+    let replay = Replay::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay, game.replay());
+
+    // Played back on the clock too, at an uneven frame rate
+    let mut player = ReplayPlayer::new(replay);
+    let mut frame = 0;
+    while player.game().tick() < game.tick() {
+        player.update([0.016, 0.017, 0.033][frame % 3]);
+        frame += 1;
+        assert!(frame < 100_000, "replay never caught up");
+    }
+    assert_same_game(player.game(), &game);
+}
+
+#[test]
+fn replays_started_in_the_same_second_keep_their_own_files() {
+    let dir = std::env::temp_dir().join(format!("snake_replays_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let game = Game::with_settings(settings(BoundaryMode::Wrap), 5);
+
+    let first = game.save_replay(&dir).unwrap();
+    let second = game.save_replay(&dir).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(Replay::load(&first).unwrap(), game.replay());
+    assert_eq!(Replay::load(&second).unwrap(), game.replay());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
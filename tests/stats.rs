@@ -0,0 +1,177 @@
+//! Stats written to a scratch directory and read back.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rust_snake_game::{
+    BoundaryMode, Difficulty, GameSettings, GameStats, RoundResult, StatsFormat, StatsHistory,
+    Versus, VersusOutcome, CSV_FILE_NAME,
+};
+
+/// An empty directory of its own under the system temp directory.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snake_stats_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn csv_rows_read_back() {
+    let dir = scratch_dir("fresh");
+    let settings = GameSettings {
+        width: 15,
+        difficulty: Difficulty::Hard,
+        ..GameSettings::default()
+    };
+    for score in [3, 7] {
+        GameStats::new(1, settings)
+            .save(&dir, StatsFormat::Csv, score, 4)
+            .unwrap();
+    }
+
+    let history = StatsHistory::load(&dir).unwrap();
+    assert_eq!(history.skipped, 0);
+    assert_eq!(history.records.len(), 2);
+    assert_eq!(history.records[0].width, 15);
+    assert_eq!(history.records[0].difficulty, Difficulty::Hard);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn csv_started_by_an_older_version_keeps_its_columns() {
+    let dir = scratch_dir("old_header");
+    // The header before board sizes, board clears and difficulties
+    let header = "started_at,timestamp,seed,mode,time_played_secs,\
+        final_score,final_length,food_eaten,up_turns,down_turns,left_turns,right_turns,total_turns";
+    let old_row = "2025-03-01T14:25:01+00:00,1740839101,42,wrap,12.500,5,8,5,1,2,3,4,10";
+    fs::write(
+        dir.join(CSV_FILE_NAME),
+        format!("{}\n{}\n", header, old_row),
+    )
+    .unwrap();
+
+    GameStats::new(1, GameSettings::default())
+        .save(&dir, StatsFormat::Csv, 9, 12)
+        .unwrap();
+
+    let contents = fs::read_to_string(dir.join(CSV_FILE_NAME)).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], header);
+    assert_eq!(lines[2].split(',').count(), header.split(',').count());
+
+    let history = StatsHistory::load(&dir).unwrap();
+    assert_eq!(history.skipped, 0);
+    let scores: Vec<u32> = history.records.iter().map(|r| r.final_score).collect();
+    assert_eq!(scores, [5, 9]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn games_started_in_the_same_second_keep_their_own_files() {
+    let dir = scratch_dir("same_second");
+    let stats = GameStats::new(1, GameSettings::default());
+
+    let text = [
+        stats.save(&dir, StatsFormat::Text, 3, 4).unwrap(),
+        stats.save(&dir, StatsFormat::Text, 5, 6).unwrap(),
+    ];
+    assert_ne!(text[0], text[1]);
+    assert!(fs::read_to_string(&text[0])
+        .unwrap()
+        .contains("Final score: 3"));
+    assert!(fs::read_to_string(&text[1])
+        .unwrap()
+        .contains("Final score: 5"));
+
+    for score in [3, 5] {
+        stats.save(&dir, StatsFormat::Json, score, 4).unwrap();
+    }
+    let history = StatsHistory::load(&dir).unwrap();
+    let mut scores: Vec<u32> = history
+        .records
+        .iter()
+        .map(|record| record.final_score)
+        .collect();
+    scores.sort();
+    assert_eq!(scores, [3, 5]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn versus_rounds_save_a_game_per_player() {
+    let settings = GameSettings {
+        boundary: BoundaryMode::Walls,
+        ..GameSettings::default()
+    };
+    let mut round = Versus::with_settings(settings, 4);
+    while round.step() {}
+
+    for format in StatsFormat::ALL {
+        let dir = scratch_dir(&format!("versus_{}", format));
+        let paths = round.save_stats(&dir, format).unwrap();
+        assert_eq!(paths.len(), 2);
+        // CSV rows share a file, every other format gets a file per player
+        assert_eq!(paths[0] == paths[1], format == StatsFormat::Csv);
+        if format != StatsFormat::Text {
+            assert_eq!(StatsHistory::load(&dir).unwrap().records.len(), 2);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn versus_records_are_marked_and_kept_out_of_the_scores() {
+    let settings = GameSettings {
+        boundary: BoundaryMode::Walls,
+        ..GameSettings::default()
+    };
+    let mut round = Versus::with_settings(settings, 4);
+    while round.step() {}
+    let result = round.result().unwrap();
+
+    for format in [StatsFormat::Json, StatsFormat::Csv] {
+        let dir = scratch_dir(&format!("versus_summary_{}", format));
+        round.save_stats(&dir, format).unwrap();
+        GameStats::new(1, settings)
+            .save(&dir, format, 3, 4)
+            .unwrap();
+
+        let history = StatsHistory::load(&dir).unwrap();
+        assert_eq!(history.skipped, 0);
+        let mut players: Vec<_> = history
+            .records
+            .iter()
+            .map(|record| (record.player, record.versus_result))
+            .collect();
+        players.sort_by_key(|(player, _)| *player);
+        assert_eq!(
+            players,
+            [
+                (None, None),
+                (Some(1), Some(result.outcome(0))),
+                (Some(2), Some(result.outcome(1))),
+            ]
+        );
+
+        let summary = history.summary();
+        assert_eq!(summary.games, 1);
+        assert_eq!(summary.best_score, 3);
+        assert_eq!(summary.versus_rounds, 1);
+        let expected = match result {
+            RoundResult::Winner(0) => ([1, 0], 0),
+            RoundResult::Winner(_) => ([0, 1], 0),
+            RoundResult::Draw => ([0, 0], 1),
+        };
+        assert_eq!((summary.versus_wins, summary.versus_draws), expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn round_results_are_seen_from_each_side() {
+    assert_eq!(RoundResult::Winner(1).outcome(1), VersusOutcome::Win);
+    assert_eq!(RoundResult::Winner(1).outcome(0), VersusOutcome::Loss);
+    assert_eq!(RoundResult::Draw.outcome(0), VersusOutcome::Draw);
+}
@@ -0,0 +1,160 @@
+//! Versus rounds set up on hand-made boards, checking who crashes and who
+//! wins.
+
+use rust_snake_game::{
+    BoundaryMode, Direction, Position, RoundResult, Versus, VersusOutcome, VersusSnapshot,
+};
+
+mod common;
+
+use common::settings;
+
+fn at(x: u32, y: u32) -> Position {
+    Position { x, y }
+}
+
+/// A walled 12x10 board with the snakes (head first), their directions and
+/// their scores as given, and the food in a corner out of everyone's way.
+fn snapshot(snakes: [(&[Position], Direction); 2], scores: [u32; 2]) -> VersusSnapshot {
+    let settings = settings(BoundaryMode::Walls);
+    let mut snapshot = Versus::with_settings(settings, 1).snapshot();
+    for (player, ((snake, direction), score)) in snapshot
+        .players
+        .iter_mut()
+        .zip(snakes.into_iter().zip(scores))
+    {
+        player.snake = snake.to_vec();
+        player.direction = direction;
+        player.queued.clear();
+        player.score = score;
+        player.food_eaten = score;
+    }
+    let taken: Vec<Position> = snakes
+        .iter()
+        .flat_map(|(snake, _)| snake.to_vec())
+        .collect();
+    snapshot.free = (0..settings.height)
+        .flat_map(|y| (0..settings.width).map(move |x| at(x, y)))
+        .filter(|cell| !taken.contains(cell))
+        .collect();
+    snapshot.food = at(0, 0);
+    snapshot
+}
+
+fn round(snakes: [(&[Position], Direction); 2], scores: [u32; 2]) -> Versus {
+    Versus::from_snapshot(&snapshot(snakes, scores)).unwrap()
+}
+
+fn alive(round: &Versus) -> [bool; 2] {
+    round.players().each_ref().map(|player| player.is_alive())
+}
+
+fn outcomes(round: &Versus) -> [Option<VersusOutcome>; 2] {
+    round
+        .players()
+        .each_ref()
+        .map(|player| player.stats().versus_result)
+}
+
+#[test]
+fn head_to_head_takes_both_out() {
+    let mut round = round(
+        [
+            (&[at(3, 5)], Direction::Right),
+            (&[at(5, 5)], Direction::Left),
+        ],
+        [2, 1],
+    );
+    assert!(round.step());
+    assert_eq!(alive(&round), [false, false]);
+    // Both crashed, so the higher score wins
+    assert_eq!(round.result(), Some(RoundResult::Winner(0)));
+    assert_eq!(
+        outcomes(&round),
+        [Some(VersusOutcome::Win), Some(VersusOutcome::Loss)]
+    );
+}
+
+#[test]
+fn heads_swapping_cells_count_as_body_hits() {
+    let mut round = round(
+        [
+            (&[at(3, 5)], Direction::Right),
+            (&[at(4, 5)], Direction::Left),
+        ],
+        [0, 0],
+    );
+    round.step();
+    assert_eq!(alive(&round), [false, false]);
+    assert_eq!(round.result(), Some(RoundResult::Draw));
+}
+
+#[test]
+fn running_into_the_other_body_loses() {
+    let mut round = round(
+        [
+            (&[at(3, 5), at(2, 5)], Direction::Right),
+            (&[at(4, 6), at(4, 5), at(4, 4)], Direction::Down),
+        ],
+        // The survivor wins whatever the scores
+        [5, 0],
+    );
+    round.step();
+    assert_eq!(alive(&round), [false, true]);
+    assert_eq!(round.result(), Some(RoundResult::Winner(1)));
+    assert_eq!(
+        outcomes(&round),
+        [Some(VersusOutcome::Loss), Some(VersusOutcome::Win)]
+    );
+    assert!(!round.step(), "the round is over");
+}
+
+#[test]
+fn crashing_on_the_same_tick_goes_to_the_scores() {
+    for (scores, result) in [
+        ([1, 3], RoundResult::Winner(1)),
+        ([4, 2], RoundResult::Winner(0)),
+        ([2, 2], RoundResult::Draw),
+    ] {
+        // Both run into the walls on opposite sides
+        let mut round = round(
+            [
+                (&[at(0, 5)], Direction::Left),
+                (&[at(11, 5)], Direction::Right),
+            ],
+            scores,
+        );
+        round.step();
+        assert_eq!(alive(&round), [false, false]);
+        assert_eq!(round.result(), Some(result));
+    }
+}
+
+#[test]
+fn equal_scores_draw_for_both_players() {
+    let mut round = round(
+        [
+            (&[at(0, 5)], Direction::Left),
+            (&[at(11, 5)], Direction::Right),
+        ],
+        [2, 2],
+    );
+    round.step();
+    assert_eq!(
+        outcomes(&round),
+        [Some(VersusOutcome::Draw), Some(VersusOutcome::Draw)]
+    );
+}
+
+#[test]
+fn snapshots_with_food_on_a_snake_are_refused() {
+    let mut snapshot = snapshot(
+        [
+            (&[at(3, 5)], Direction::Right),
+            (&[at(8, 5)], Direction::Left),
+        ],
+        [0, 0],
+    );
+    snapshot.food = at(8, 5);
+    assert!(Versus::from_snapshot(&snapshot).is_err());
+}